@@ -0,0 +1,10 @@
+use clap::ValueEnum;
+
+/// How requests from a listed anonymizer range are treated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum AnonymizerPolicy {
+    /// Refuse the request
+    Block,
+    /// Apply the anonymizer cooldown instead of the regular one
+    TighterLimit,
+}
@@ -0,0 +1,59 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use alloy_primitives::{Address, U256};
+use alloy_provider::Provider;
+use alloy_transport::TransportResult;
+
+use crate::FaucetProvider;
+
+/// The faucet wallet's balance, refreshed from the node once older than `max_age`.
+pub struct BalanceCache {
+    max_age: Duration,
+    cached: Mutex<Option<(Instant, U256)>>,
+}
+
+impl BalanceCache {
+    /// A zero `max_age` fetches the balance on every call.
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            max_age,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// Balance of `address`, served from cache while fresh.
+    pub async fn get(&self, provider: &FaucetProvider, address: Address) -> TransportResult<U256> {
+        if let Some((fetched_at, balance)) = *self.cached.lock().unwrap() {
+            if fetched_at.elapsed() < self.max_age {
+                return Ok(balance);
+            }
+        }
+
+        let balance = provider.get_balance(address).await?;
+        *self.cached.lock().unwrap() = Some((Instant::now(), balance));
+        Ok(balance)
+    }
+
+    /// Deducts a sent drip so a burst can't spend the same cached balance twice.
+    pub fn debit(&self, amount: U256) {
+        if let Some((_, balance)) = self.cached.lock().unwrap().as_mut() {
+            *balance = balance.saturating_sub(amount);
+        }
+    }
+
+    /// Forces the next call to refetch the balance.
+    pub fn invalidate(&self) {
+        *self.cached.lock().unwrap() = None;
+    }
+
+    /// Time since the cached balance was fetched.
+    pub fn age(&self) -> Option<Duration> {
+        self.cached
+            .lock()
+            .unwrap()
+            .map(|(fetched_at, _)| fetched_at.elapsed())
+    }
+}
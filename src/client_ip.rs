@@ -0,0 +1,230 @@
+use std::net::{IpAddr, SocketAddr};
+
+use actix_web::{http::header, HttpRequest};
+use tracing::debug;
+
+use crate::ip_ranges::IpRanges;
+
+/// Longest X-Forwarded-For value parsed, anything larger is ignored.
+const MAX_FORWARDED_BYTES: usize = 2048;
+
+/// Determines the IP a request came from, following X-Forwarded-For only through trusted proxies.
+pub struct ClientIpResolver {
+    trusted_proxies: IpRanges,
+    max_hops: usize,
+}
+
+impl ClientIpResolver {
+    pub fn new(trusted_proxies: IpRanges, max_hops: usize) -> Self {
+        Self {
+            trusted_proxies,
+            max_hops,
+        }
+    }
+
+    /// The rightmost forwarded address not belonging to a trusted proxy.
+    ///
+    /// Entries left of it were written by the client and can't be relied on. Oversized or
+    /// non-UTF-8 headers are ignored, and a malformed hop or one past `max_hops` ends the walk
+    /// at the last trusted address, so a bad header only ever attributes the request to a proxy.
+    pub fn resolve(&self, req: &HttpRequest) -> Option<IpAddr> {
+        let peer = req.peer_addr()?.ip().to_canonical();
+        if !self.trusted_proxies.contains(peer) {
+            return Some(peer);
+        }
+
+        let mut values = Vec::new();
+        let mut len = 0;
+        for value in req.headers().get_all(header::X_FORWARDED_FOR) {
+            len += value.len();
+            if len > MAX_FORWARDED_BYTES {
+                debug!(
+                    "ignoring X-Forwarded-For longer than {} bytes",
+                    MAX_FORWARDED_BYTES
+                );
+                return Some(peer);
+            }
+            match value.to_str() {
+                Ok(value) => values.push(value),
+                Err(_) => {
+                    debug!("ignoring X-Forwarded-For that is not valid UTF-8");
+                    return Some(peer);
+                }
+            }
+        }
+
+        // Later header lines were appended by proxies closer to us
+        let mut client = peer;
+        let hops = values.iter().rev().flat_map(|value| value.rsplit(','));
+        for hop in hops.take(self.max_hops) {
+            let Some(ip) = parse_hop(hop) else {
+                debug!("stopping at malformed X-Forwarded-For hop {:?}", hop);
+                break;
+            };
+            client = ip;
+            if !self.trusted_proxies.contains(ip) {
+                break;
+            }
+        }
+        Some(client)
+    }
+}
+
+/// An address with an optional port, e.g. `203.0.113.7`, `203.0.113.7:4711` or `[2001:db8::1]:4711`.
+fn parse_hop(hop: &str) -> Option<IpAddr> {
+    let hop = hop.trim();
+    hop.parse::<IpAddr>()
+        .or_else(|_| hop.parse::<SocketAddr>().map(|addr| addr.ip()))
+        .ok()
+        .map(|ip| ip.to_canonical())
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::header::HeaderValue, test::TestRequest};
+
+    use super::*;
+
+    fn resolver(trusted: &[&str], max_hops: usize) -> ClientIpResolver {
+        ClientIpResolver::new(IpRanges::parse(trusted.iter().copied()).unwrap(), max_hops)
+    }
+
+    fn resolve(resolver: &ClientIpResolver, peer: &str, forwarded: &[&str]) -> IpAddr {
+        let mut req = TestRequest::default().peer_addr(SocketAddr::new(peer.parse().unwrap(), 443));
+        for value in forwarded {
+            req = req.append_header((header::X_FORWARDED_FOR, *value));
+        }
+        resolver.resolve(&req.to_http_request()).unwrap()
+    }
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    #[test]
+    fn untrusted_peer_is_the_client() {
+        let resolver = resolver(&["10.0.0.0/8"], 10);
+        assert_eq!(
+            resolve(&resolver, "198.51.100.1", &["203.0.113.7"]),
+            ip("198.51.100.1")
+        );
+    }
+
+    #[test]
+    fn walks_trusted_proxies_to_the_rightmost_untrusted_hop() {
+        let resolver = resolver(&["10.0.0.0/8"], 10);
+        // The leftmost entry was written by the client and is ignored
+        assert_eq!(
+            resolve(
+                &resolver,
+                "10.0.0.1",
+                &["1.2.3.4, 203.0.113.7, 10.0.0.3, 10.0.0.2"]
+            ),
+            ip("203.0.113.7")
+        );
+        // Later header lines were appended by nearer proxies
+        assert_eq!(
+            resolve(&resolver, "10.0.0.1", &["1.2.3.4, 203.0.113.7", "10.0.0.2"]),
+            ip("203.0.113.7")
+        );
+    }
+
+    #[test]
+    fn all_trusted_chain_ends_at_the_last_hop() {
+        let resolver = resolver(&["10.0.0.0/8"], 10);
+        assert_eq!(
+            resolve(&resolver, "10.0.0.1", &["10.0.0.3, 10.0.0.2"]),
+            ip("10.0.0.3")
+        );
+        assert_eq!(resolve(&resolver, "10.0.0.1", &[]), ip("10.0.0.1"));
+    }
+
+    #[test]
+    fn garbage_hop_stops_at_the_last_trusted_address() {
+        let resolver = resolver(&["10.0.0.0/8"], 10);
+        for garbage in ["not-an-ip", "", "999.1.1.1", "203.0.113.7:port", "unknown"] {
+            let forwarded = format!("203.0.113.7, {}, 10.0.0.2", garbage);
+            assert_eq!(
+                resolve(&resolver, "10.0.0.1", &[&forwarded]),
+                ip("10.0.0.2"),
+                "{:?}",
+                garbage
+            );
+        }
+    }
+
+    #[test]
+    fn hops_may_carry_ports() {
+        let resolver = resolver(&["10.0.0.0/8", "2001:db8:ffff::/48"], 10);
+        assert_eq!(
+            resolve(&resolver, "10.0.0.1", &["203.0.113.7:4711"]),
+            ip("203.0.113.7")
+        );
+        assert_eq!(
+            resolve(
+                &resolver,
+                "10.0.0.1",
+                &["[2001:db8::1]:4711, [2001:db8:ffff::2]:80"]
+            ),
+            ip("2001:db8::1")
+        );
+        assert_eq!(
+            resolve(&resolver, "10.0.0.1", &[" 2001:db8::1 "]),
+            ip("2001:db8::1")
+        );
+        assert_eq!(
+            resolve(&resolver, "10.0.0.1", &["::ffff:203.0.113.7"]),
+            ip("203.0.113.7")
+        );
+    }
+
+    #[test]
+    fn mapped_peer_address_is_matched_as_ipv4() {
+        let resolver = resolver(&["10.0.0.0/8"], 10);
+        assert_eq!(
+            resolve(&resolver, "::ffff:10.0.0.1", &["203.0.113.7"]),
+            ip("203.0.113.7")
+        );
+    }
+
+    #[test]
+    fn walk_is_bounded_by_max_hops() {
+        let resolver = resolver(&["10.0.0.0/8"], 3);
+        assert_eq!(
+            resolve(
+                &resolver,
+                "10.0.0.1",
+                &["203.0.113.7, 10.0.0.5, 10.0.0.4, 10.0.0.3, 10.0.0.2"]
+            ),
+            ip("10.0.0.4")
+        );
+    }
+
+    #[test]
+    fn oversized_chain_is_ignored() {
+        let resolver = resolver(&["10.0.0.0/8"], 10);
+        let chain = vec!["10.0.0.9"; 300].join(", ");
+        assert!(chain.len() > MAX_FORWARDED_BYTES);
+        assert_eq!(resolve(&resolver, "10.0.0.1", &[&chain]), ip("10.0.0.1"));
+
+        // The limit applies across header lines too
+        let line = vec!["10.0.0.9"; 100].join(", ");
+        assert_eq!(
+            resolve(&resolver, "10.0.0.1", &[&line, &line, &line]),
+            ip("10.0.0.1")
+        );
+    }
+
+    #[test]
+    fn non_utf8_header_is_ignored() {
+        let resolver = resolver(&["10.0.0.0/8"], 10);
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.1:443".parse().unwrap())
+            .insert_header((
+                header::X_FORWARDED_FOR,
+                HeaderValue::from_bytes(b"203.0.113.7\xff").unwrap(),
+            ))
+            .to_http_request();
+        assert_eq!(resolver.resolve(&req), Some(ip("10.0.0.1")));
+    }
+}
@@ -0,0 +1,81 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use alloy_primitives::Address;
+use serde::{Deserialize, Serialize};
+
+/// How long an address the service allowed is trusted before asking again.
+const ELIGIBILITY_CACHE_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Serialize)]
+struct EligibilityRequest<'a> {
+    address: String,
+    ip: Option<IpAddr>,
+    client_ref: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct EligibilityResponse {
+    allowed: bool,
+}
+
+/// Delegates the decision to fund an address to an external HTTP service.
+pub struct EligibilityService {
+    client: reqwest::Client,
+    url: String,
+    timeout: Duration,
+    allowed: Mutex<HashMap<Address, Instant>>,
+}
+
+impl EligibilityService {
+    pub fn new(url: String, timeout: Duration) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+            timeout,
+            allowed: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether the service allows funding `address`, served from cache for recently allowed ones.
+    pub async fn is_allowed(
+        &self,
+        address: Address,
+        ip: Option<IpAddr>,
+        client_ref: Option<&str>,
+    ) -> Result<bool, String> {
+        {
+            let mut allowed = self.allowed.lock().unwrap();
+            allowed.retain(|_, at| at.elapsed() < ELIGIBILITY_CACHE_TTL);
+            if allowed.contains_key(&address) {
+                return Ok(true);
+            }
+        }
+
+        let response: EligibilityResponse = self
+            .client
+            .post(&self.url)
+            .timeout(self.timeout)
+            .json(&EligibilityRequest {
+                address: address.to_string(),
+                ip,
+                client_ref,
+            })
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| e.to_string())?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if response.allowed {
+            self.allowed.lock().unwrap().insert(address, Instant::now());
+        }
+        Ok(response.allowed)
+    }
+}
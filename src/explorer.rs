@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+use alloy_primitives::TxHash;
+use serde::Deserialize;
+
+/// Etherscan-compatible API response, also served by Blockscout.
+#[derive(Deserialize)]
+struct ApiResponse {
+    status: String,
+    message: String,
+    result: serde_json::Value,
+}
+
+/// Looks up transaction outcomes on a block explorer when the RPC can't be relied on.
+pub struct Explorer {
+    client: reqwest::Client,
+    url: String,
+    api_key: Option<String>,
+}
+
+impl Explorer {
+    pub fn new(url: String, api_key: Option<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+            api_key,
+        }
+    }
+
+    /// Whether the transaction succeeded, or `None` if the explorer doesn't know its outcome yet.
+    pub async fn receipt_status(&self, tx_hash: TxHash) -> Result<Option<bool>, String> {
+        let tx_hash = tx_hash.to_string();
+        let mut query = vec![
+            ("module", "transaction"),
+            ("action", "gettxreceiptstatus"),
+            ("txhash", tx_hash.as_str()),
+        ];
+        if let Some(api_key) = &self.api_key {
+            query.push(("apikey", api_key));
+        }
+
+        let response: ApiResponse = self
+            .client
+            .get(&self.url)
+            .query(&query)
+            .timeout(Duration::from_secs(5))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| e.to_string())?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        // Rate limits and other failures come back as status 0 with the reason in result
+        if response.status != "1" {
+            return Err(format!("{}: {}", response.message, response.result));
+        }
+        match response.result.get("status").and_then(|s| s.as_str()) {
+            Some("1") => Ok(Some(true)),
+            Some("0") => Ok(Some(false)),
+            _ => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use alloy_provider::Provider;
+use alloy_rpc_types::BlockNumberOrTag;
+
+use crate::FaucetProvider;
+
+/// How long a priority fee floor is reused before querying the fee history again.
+const FEE_FLOOR_CACHE_TTL: Duration = Duration::from_secs(12);
+
+/// Number of recent blocks sampled from eth_feeHistory.
+const FEE_HISTORY_BLOCKS: u64 = 10;
+
+/// Fees per gas read from recent blocks, in wei.
+#[derive(Clone, Copy, Debug)]
+pub struct RecentFees {
+    /// Base fee of the next block
+    pub base_fee: u128,
+    /// Priority fee paid at the configured percentile
+    pub priority_fee: u128,
+}
+
+/// Tracks the priority fee recent blocks paid at a given percentile.
+pub struct PriorityFeeFloor {
+    percentile: f64,
+    cached: Mutex<Option<(Instant, RecentFees)>>,
+}
+
+impl PriorityFeeFloor {
+    pub fn new(percentile: f64) -> Self {
+        Self {
+            percentile,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// Next block's base fee, and the median over recent blocks of the priority fee paid at the
+    /// configured percentile.
+    pub async fn floor(&self, provider: &FaucetProvider) -> Result<RecentFees, String> {
+        if let Some((fetched_at, fees)) = *self.cached.lock().unwrap() {
+            if fetched_at.elapsed() < FEE_FLOOR_CACHE_TTL {
+                return Ok(fees);
+            }
+        }
+
+        let history = provider
+            .get_fee_history(
+                FEE_HISTORY_BLOCKS,
+                BlockNumberOrTag::Latest,
+                &[self.percentile],
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut rewards: Vec<u128> = history
+            .reward
+            .as_deref()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|block| block.first().copied())
+            .collect();
+        if rewards.is_empty() {
+            return Err("fee history has no priority fee rewards".to_string());
+        }
+        rewards.sort_unstable();
+        let fees = RecentFees {
+            base_fee: history.next_block_base_fee().unwrap_or_default(),
+            priority_fee: rewards[rewards.len() / 2],
+        };
+
+        *self.cached.lock().unwrap() = Some((Instant::now(), fees));
+        Ok(fees)
+    }
+}
@@ -0,0 +1,157 @@
+use std::{net::IpAddr, path::Path};
+
+/// A set of IP addresses and CIDR ranges.
+#[derive(Default)]
+pub struct IpRanges {
+    ranges: Vec<(IpAddr, u8)>,
+}
+
+impl IpRanges {
+    /// Parses addresses or CIDR ranges such as `10.0.0.0/8`, returning the first invalid one on error.
+    pub fn parse<'a>(ranges: impl IntoIterator<Item = &'a str>) -> Result<Self, &'a str> {
+        let ranges = ranges
+            .into_iter()
+            .map(|range| parse_range(range).ok_or(range))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { ranges })
+    }
+
+    /// Loads a file with one address or CIDR range per line, ignoring blank lines and `#` comments.
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        let lines = contents
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default().trim())
+            .filter(|line| !line.is_empty());
+        Self::parse(lines).map_err(|range| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Invalid IP range {} in {}", range, path.display()),
+            )
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        self.ranges
+            .iter()
+            .any(|(network, prefix)| in_range(ip, *network, *prefix))
+    }
+}
+
+fn parse_range(range: &str) -> Option<(IpAddr, u8)> {
+    let (network, prefix) = match range.split_once('/') {
+        Some((network, prefix)) => (network, Some(prefix.parse().ok()?)),
+        None => (range, None),
+    };
+    let parsed = network.parse::<IpAddr>().ok()?;
+    let max_prefix = if parsed.is_ipv4() { 32 } else { 128 };
+    let prefix = prefix.unwrap_or(max_prefix);
+    if prefix > max_prefix {
+        return None;
+    }
+
+    // IPv4-mapped ranges are matched as IPv4, which needs the prefix of the embedded address
+    let network = parsed.to_canonical();
+    match (parsed, network) {
+        (IpAddr::V6(_), IpAddr::V4(_)) if prefix < 96 => Some((parsed, prefix)),
+        (IpAddr::V6(_), IpAddr::V4(_)) => Some((network, prefix - 96)),
+        _ => Some((network, prefix)),
+    }
+}
+
+fn in_range(ip: IpAddr, network: IpAddr, prefix: u8) -> bool {
+    match (ip, network) {
+        (IpAddr::V4(ip), IpAddr::V4(network)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(network)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(network) & mask
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranges(ranges: &[&str]) -> IpRanges {
+        IpRanges::parse(ranges.iter().copied()).unwrap()
+    }
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    #[test]
+    fn ipv4_prefix_matches_its_network_only() {
+        let ranges = ranges(&["10.1.0.0/16"]);
+        assert!(ranges.contains(ip("10.1.0.0")));
+        assert!(ranges.contains(ip("10.1.255.255")));
+        assert!(!ranges.contains(ip("10.2.0.0")));
+        assert!(!ranges.contains(ip("fd00::1")));
+    }
+
+    #[test]
+    fn ipv6_prefix_matches_its_network_only() {
+        let ranges = ranges(&["2001:db8::/32"]);
+        assert!(ranges.contains(ip("2001:db8::1")));
+        assert!(ranges.contains(ip("2001:db8:ffff::ffff")));
+        assert!(!ranges.contains(ip("2001:db9::1")));
+        assert!(!ranges.contains(ip("10.0.0.1")));
+    }
+
+    #[test]
+    fn zero_prefix_matches_the_whole_family() {
+        let v4 = ranges(&["0.0.0.0/0"]);
+        assert!(v4.contains(ip("203.0.113.7")));
+        assert!(v4.contains(ip("255.255.255.255")));
+        assert!(!v4.contains(ip("2001:db8::1")));
+
+        let v6 = ranges(&["::/0"]);
+        assert!(v6.contains(ip("2001:db8::1")));
+        assert!(!v6.contains(ip("203.0.113.7")));
+    }
+
+    #[test]
+    fn full_prefix_matches_a_single_address() {
+        let ranges = ranges(&["203.0.113.7/32", "2001:db8::7/128", "198.51.100.1"]);
+        assert!(ranges.contains(ip("203.0.113.7")));
+        assert!(!ranges.contains(ip("203.0.113.8")));
+        assert!(ranges.contains(ip("2001:db8::7")));
+        assert!(!ranges.contains(ip("2001:db8::8")));
+        assert!(ranges.contains(ip("198.51.100.1")));
+        assert!(!ranges.contains(ip("198.51.100.2")));
+    }
+
+    #[test]
+    fn prefixes_past_the_address_length_are_invalid() {
+        assert_eq!(IpRanges::parse(["10.0.0.0/33"]).err(), Some("10.0.0.0/33"));
+        assert_eq!(IpRanges::parse(["::/129"]).err(), Some("::/129"));
+        assert_eq!(IpRanges::parse(["10.0.0.0/x"]).err(), Some("10.0.0.0/x"));
+    }
+
+    #[test]
+    fn ipv4_mapped_addresses_match_ipv4_ranges() {
+        let ranges = ranges(&["10.1.0.0/16"]);
+        assert!(ranges.contains(ip("::ffff:10.1.2.3")));
+        assert!(!ranges.contains(ip("::ffff:10.2.0.1")));
+    }
+
+    #[test]
+    fn ipv4_mapped_ranges_match_ipv4_addresses() {
+        let ranges = ranges(&["::ffff:10.1.0.0/112", "::ffff:198.51.100.1"]);
+        assert!(ranges.contains(ip("10.1.2.3")));
+        assert!(ranges.contains(ip("::ffff:10.1.2.3")));
+        assert!(!ranges.contains(ip("10.2.0.1")));
+        assert!(ranges.contains(ip("198.51.100.1")));
+        assert!(!ranges.contains(ip("198.51.100.2")));
+    }
+}
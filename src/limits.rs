@@ -0,0 +1,147 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Caps the number of simultaneous requests from a single client IP.
+pub struct InFlightLimiter {
+    max_per_ip: usize,
+    counts: Mutex<HashMap<IpAddr, usize>>,
+}
+
+/// Releases an in-flight slot when dropped, including when the request is cancelled.
+pub struct InFlightGuard<'a> {
+    limiter: &'a InFlightLimiter,
+    ip: IpAddr,
+}
+
+impl InFlightLimiter {
+    /// A `max_per_ip` of zero disables the cap.
+    pub fn new(max_per_ip: usize) -> Self {
+        Self {
+            max_per_ip,
+            counts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Takes an in-flight slot for `ip`, or returns `None` if it already has the maximum.
+    pub fn try_acquire(&self, ip: IpAddr) -> Option<InFlightGuard<'_>> {
+        let mut counts = self.counts.lock().unwrap();
+        let count = counts.entry(ip).or_default();
+        if self.max_per_ip > 0 && *count >= self.max_per_ip {
+            return None;
+        }
+        *count += 1;
+        Some(InFlightGuard { limiter: self, ip })
+    }
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        let mut counts = self.limiter.counts.lock().unwrap();
+        if let Some(count) = counts.get_mut(&self.ip) {
+            *count -= 1;
+            if *count == 0 {
+                counts.remove(&self.ip);
+            }
+        }
+    }
+}
+
+/// Enforces a minimum interval between any two drips across all clients.
+pub struct IntervalGate {
+    min_interval: Duration,
+    last_pass: Mutex<Option<Instant>>,
+}
+
+/// A slot taken from the gate, handed back when dropped without being committed so a request that
+/// didn't dispense doesn't hold up the next one.
+pub struct IntervalPass<'a> {
+    gate: &'a IntervalGate,
+    /// When the slot was taken, `None` while the gate is disabled
+    taken: Option<Instant>,
+    previous: Option<Instant>,
+}
+
+impl IntervalGate {
+    /// A zero `min_interval` disables the gate.
+    pub fn new(min_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            last_pass: Mutex::new(None),
+        }
+    }
+
+    /// Takes the next slot, or returns how long until one is available.
+    pub fn try_pass(&self) -> Result<IntervalPass<'_>, Duration> {
+        if self.min_interval.is_zero() {
+            return Ok(IntervalPass {
+                gate: self,
+                taken: None,
+                previous: None,
+            });
+        }
+
+        let now = Instant::now();
+        let mut last_pass = self.last_pass.lock().unwrap();
+        if let Some(wait) = last_pass
+            .and_then(|last| (last + self.min_interval).checked_duration_since(now))
+            .filter(|wait| !wait.is_zero())
+        {
+            return Err(wait);
+        }
+        let previous = last_pass.replace(now);
+        Ok(IntervalPass {
+            gate: self,
+            taken: Some(now),
+            previous,
+        })
+    }
+}
+
+impl IntervalPass<'_> {
+    /// Keeps the slot for a drip that was sent.
+    pub fn commit(self) {
+        std::mem::forget(self);
+    }
+}
+
+impl Drop for IntervalPass<'_> {
+    fn drop(&mut self) {
+        let mut last_pass = self.gate.last_pass.lock().unwrap();
+        // A later pass may already have been taken, it stays in place
+        if self.taken.is_some() && *last_pass == self.taken {
+            *last_pass = self.previous;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn committed_pass_holds_the_interval() {
+        let gate = IntervalGate::new(Duration::from_secs(60));
+        gate.try_pass().unwrap().commit();
+        assert!(gate.try_pass().is_err());
+    }
+
+    #[test]
+    fn dropped_pass_frees_the_slot() {
+        let gate = IntervalGate::new(Duration::from_secs(60));
+        drop(gate.try_pass().unwrap());
+        // The rejected request's slot went back, the committed one stays
+        gate.try_pass().unwrap().commit();
+        assert!(gate.try_pass().is_err());
+    }
+
+    #[test]
+    fn disabled_gate_always_passes() {
+        let gate = IntervalGate::new(Duration::ZERO);
+        gate.try_pass().unwrap().commit();
+        assert!(gate.try_pass().is_ok());
+    }
+}
@@ -1,21 +1,85 @@
-use std::{io::Result, sync::Arc};
+mod anonymizer;
+mod balance_cache;
+mod client_ip;
+mod eligibility;
+mod explorer;
+mod fee_floor;
+mod ip_ranges;
+mod limits;
+mod mempool;
+mod nonce;
+mod pending;
+mod pii;
+mod preflight;
+mod price_oracle;
+mod rejection_log;
+mod relay;
+mod response;
+mod rpc_proxy;
+mod rpc_trace;
+mod store;
+
+use std::{
+    collections::HashMap,
+    io::Result,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
 use clap::Parser;
+use futures::stream::{FuturesUnordered, StreamExt};
 
 use actix_cors::Cors;
-use actix_web::{middleware, web, App, HttpResponse, HttpServer, Responder};
+use actix_web::{
+    body::MessageBody,
+    dev::{HttpServiceFactory, ServiceRequest, ServiceResponse},
+    error::{InternalError, JsonPayloadError},
+    http::{
+        header::{self, ContentType, HeaderName, HeaderValue},
+        StatusCode,
+    },
+    middleware, web, App, HttpRequest, HttpResponse, HttpResponseBuilder, HttpServer,
+};
 
-use alloy_primitives::{Address, U256};
+use alloy_primitives::{Address, Bytes, TxHash, U256};
 use alloy_provider::{
-    network::{EthereumWallet, TransactionBuilder},
+    network::{eip2718::Encodable2718, EthereumWallet, TransactionBuilder},
     Provider, ProviderBuilder, RootProvider,
 };
-use alloy_rpc_types::TransactionRequest;
+use alloy_rpc_client::ClientBuilder;
+use alloy_rpc_types::{BlockNumberOrTag, TransactionReceipt, TransactionRequest};
+use alloy_signer::SignerSync;
 use alloy_signer_local::PrivateKeySigner;
+use alloy_transport::TransportError;
 use alloy_transport_http::{Client, Http};
 
 use serde::{Deserialize, Serialize};
-use tracing::info;
+use tracing::{error, info, warn};
+
+use anonymizer::AnonymizerPolicy;
+use balance_cache::BalanceCache;
+use client_ip::ClientIpResolver;
+use eligibility::EligibilityService;
+use explorer::Explorer;
+use fee_floor::{PriorityFeeFloor, RecentFees};
+use ip_ranges::IpRanges;
+use limits::{InFlightLimiter, IntervalGate};
+use mempool::MempoolMonitor;
+use nonce::NonceTracker;
+use pending::{PendingDrips, ReserveError};
+use pii::Pseudonymizer;
+use price_oracle::PriceOracle;
+use rejection_log::RejectionLog;
+use relay::PrivateRelay;
+use response::{FaucetError, ResponseVersion};
+use rpc_proxy::{proxy_rpc, RpcProxy};
+use rpc_trace::{RpcTrace, RpcTraceLayer};
+use store::{Claim, ClaimStore, StoreBackend, HISTORY_LIMIT};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -32,6 +96,10 @@ struct Args {
     #[arg(long, default_value = "1000000000000000000")]
     tokens_per_request: String,
 
+    /// Named amount clients may request instead of the default, as NAME=WEI or NAME=<ether>eth, e.g. small=0.1eth (repeatable)
+    #[arg(long = "amount-preset")]
+    amount_presets: Vec<String>,
+
     /// Server port to listen on
     #[arg(long, default_value = "5556")]
     port: u16,
@@ -47,83 +115,1742 @@ struct Args {
     /// Gas limit for transactions
     #[arg(long, default_value = "21000")]
     gas_limit: u64,
+
+    /// Raise the gas limit to the node's eth_estimateGas result when that is higher
+    #[arg(long)]
+    estimate_gas: bool,
+
+    /// Times a drip that ran out of gas is resent with a doubled gas limit (0 disables)
+    #[arg(long, default_value = "0")]
+    max_gas_bumps: u32,
+
+    /// Lowest gas price in gwei a request may bid, at least and by default --gas-price-gwei
+    #[arg(long)]
+    min_gas_price_gwei: Option<u64>,
+
+    /// Highest gas price in gwei a request may bid
+    #[arg(long, default_value = "100")]
+    max_gas_price_gwei: u64,
+
+    /// Raise the priority fee to this percentile of what recent blocks paid, up to --max-gas-price-gwei
+    #[arg(long)]
+    priority_fee_percentile: Option<f64>,
+
+    /// Milliseconds the faucet balance is reused between requests before it is fetched again (0 always fetches)
+    #[arg(long, default_value = "2000")]
+    balance_cache_max_age_ms: u64,
+
+    /// Maximum age of the latest block (in seconds) before the chain is considered stalled
+    #[arg(long)]
+    max_block_age_secs: Option<u64>,
+
+    /// Seconds to wait for the node to accept a signed transaction
+    #[arg(long, default_value = "30")]
+    send_timeout_secs: u64,
+
+    /// Seconds a sent drip is watched for confirmation before its recipient may be funded again
+    #[arg(long, default_value = "600")]
+    confirmation_timeout_secs: u64,
+
+    /// Look up each sent transaction on the node and resend it while the node doesn't know it
+    #[arg(long)]
+    verify_broadcast: bool,
+
+    /// Reject drips with 503 while the node's txpool_status reports more pending transactions than this
+    #[arg(long)]
+    max_mempool_pending: Option<u64>,
+
+    /// Expose a read-only JSON-RPC passthrough to the backing node at /rpc
+    #[arg(long)]
+    enable_rpc_proxy: bool,
+
+    /// RPC method forwarded by the passthrough (repeatable, read-only methods only)
+    #[arg(
+        long = "rpc-proxy-method",
+        default_values = ["eth_blockNumber", "eth_chainId", "eth_gasPrice", "eth_getBalance"]
+    )]
+    rpc_proxy_methods: Vec<String>,
+
+    /// Maximum passthrough requests per minute from a single client IP
+    #[arg(long, default_value = "60")]
+    rpc_proxy_requests_per_minute: u32,
+
+    /// Expose the estimated cost per drip and the drips left in the balance at /cost-estimate
+    #[arg(long)]
+    enable_cost_estimate: bool,
+
+    /// JSON file mapping a category name to addresses that must never be funded
+    #[arg(long)]
+    known_addresses_file: Option<PathBuf>,
+
+    /// Proxy address or CIDR range whose X-Forwarded-For header is trusted (repeatable)
+    #[arg(long = "trusted-proxy")]
+    trusted_proxies: Vec<String>,
+
+    /// Maximum number of X-Forwarded-For hops followed through trusted proxies
+    #[arg(long, default_value = "10")]
+    max_forwarded_hops: usize,
+
+    /// File of IP addresses or CIDR ranges of anonymizing networks such as Tor exits or VPNs, one per line
+    #[arg(long)]
+    anonymizer_ranges_file: Option<PathBuf>,
+
+    /// How requests from a listed anonymizer range are treated
+    #[arg(long, value_enum, default_value = "tighter-limit")]
+    anonymizer_policy: AnonymizerPolicy,
+
+    /// Cooldown in seconds for recipients requesting from an anonymizer range under the tighter-limit policy
+    #[arg(long, default_value = "86400")]
+    anonymizer_cooldown_secs: u64,
+
+    /// Pad /faucet responses to a minimum duration so eligibility can't be inferred from latency
+    #[arg(long)]
+    constant_time_response: bool,
+
+    /// Minimum /faucet response time in milliseconds when --constant-time-response is set
+    #[arg(long, default_value = "1000")]
+    min_response_ms: u64,
+
+    /// Log every RPC request and response at trace level (signed transactions are redacted)
+    #[arg(long)]
+    trace_rpc: bool,
+
+    /// Maximum bytes of each RPC request or response body written by --trace-rpc
+    #[arg(long, default_value = "4096")]
+    max_logged_body_bytes: usize,
+
+    /// Backend used to persist claim history
+    #[arg(long, value_enum, default_value = "memory")]
+    store_backend: StoreBackend,
+
+    /// Connection URL for the sqlite or postgres store backend
+    #[arg(long)]
+    store_url: Option<String>,
+
+    /// Maximum simultaneous /faucet requests from a single client IP (0 disables)
+    #[arg(long, default_value = "2")]
+    max_concurrent_per_ip: usize,
+
+    /// Minimum milliseconds between any two drips across all clients (0 disables)
+    #[arg(long, default_value = "0")]
+    global_min_interval_ms: u64,
+
+    /// Minimum seconds between drips to the same address (0 disables)
+    #[arg(long, default_value = "0")]
+    cooldown_secs: u64,
+
+    /// Retry connecting to the RPC at startup instead of failing when it is unreachable
+    #[arg(long)]
+    wait_for_rpc: bool,
+
+    /// Run the startup checks, log the report and exit without serving
+    #[arg(long)]
+    validate_only: bool,
+
+    /// Print the most recent drips to this address from the claim store and exit without serving
+    #[arg(long)]
+    show_history: Option<Address>,
+
+    /// Reuse nonces the node lost when it reports a lower pending nonce, instead of keeping the local one
+    #[arg(long)]
+    resync_nonce_down: bool,
+
+    /// Maximum seconds to wait for the RPC when --wait-for-rpc is set
+    #[arg(long, default_value = "60")]
+    rpc_wait_timeout_secs: u64,
+
+    /// Identifier of this faucet instance, recorded with each claim and reported by /info
+    #[arg(long)]
+    faucet_id: Option<String>,
+
+    /// Send whatever the faucet can still afford instead of rejecting once it runs low
+    #[arg(long)]
+    allow_partial_drip: bool,
+
+    /// Smallest amount (in wei) sent when --allow-partial-drip reduces a drip
+    #[arg(long, default_value = "0")]
+    min_fallback_amount: String,
+
+    /// Only fund addresses starting with this hex prefix (repeatable)
+    #[arg(long = "recipient-prefix")]
+    recipient_prefixes: Vec<String>,
+
+    /// Clear the claim store when the chain id or genesis block differs from the stored one
+    #[arg(long)]
+    reset_on_chain_change: bool,
+
+    /// URL of a JSON price feed used to dispense a fixed USD value instead of --tokens-per-request
+    #[arg(long, requires = "usd_amount")]
+    price_oracle_url: Option<String>,
+
+    /// Dotted path to the token's USD price in the price feed response
+    #[arg(long, default_value = "price")]
+    price_oracle_path: String,
+
+    /// USD value to dispense per request when --price-oracle-url is set
+    #[arg(long)]
+    usd_amount: Option<f64>,
+
+    /// Largest multiple of --tokens-per-request a --usd-amount drip may be, whatever the feed price
+    #[arg(long, default_value = "10")]
+    max_usd_amount_multiple: u64,
+
+    /// Extra header added to every response as KEY=VALUE (repeatable, overrides the security defaults)
+    #[arg(long = "response-header")]
+    response_headers: Vec<String>,
+
+    /// Embed the faucet id as calldata in every transaction (the gas limit is raised to cover it)
+    #[arg(long, requires = "faucet_id")]
+    tag_calldata: bool,
+
+    /// Additional RPC URL that signed transactions are broadcast to with --broadcast-to-all (repeatable)
+    #[arg(long = "broadcast-rpc-url")]
+    broadcast_rpc_urls: Vec<String>,
+
+    /// Broadcast every transaction to --rpc-url and all --broadcast-rpc-url endpoints at once
+    #[arg(long, requires = "broadcast_rpc_urls")]
+    broadcast_to_all: bool,
+
+    /// Submit drips to this Flashbots-style private relay with eth_sendPrivateTransaction instead of the public mempool
+    #[arg(long)]
+    private_relay_url: Option<String>,
+
+    /// Broadcast publicly when the private relay fails
+    #[arg(long, requires = "private_relay_url")]
+    relay_fallback: bool,
+
+    /// Maximum distinct addresses ever funded for requests from a single client IP
+    #[arg(long)]
+    max_addresses_per_ip: Option<u64>,
+
+    /// Service asked whether each recipient may be funded, it is POSTed the address, IP and client_ref and must answer {"allowed": bool}
+    #[arg(long)]
+    eligibility_service_url: Option<String>,
+
+    /// Milliseconds to wait for the eligibility service
+    #[arg(long, default_value = "2000")]
+    eligibility_timeout_ms: u64,
+
+    /// Dispense when the eligibility service is unreachable instead of rejecting the request
+    #[arg(long)]
+    eligibility_fail_open: bool,
+
+    /// Etherscan-compatible explorer API used to confirm drips when RPC receipt lookups fail
+    #[arg(long)]
+    explorer_api_url: Option<String>,
+
+    /// API key sent to --explorer-api-url
+    #[arg(long, requires = "explorer_api_url")]
+    explorer_api_key: Option<String>,
+
+    /// Maximum unconfirmed drips tracked at once, new requests are rejected with 503 beyond it
+    #[arg(long, default_value = "1000")]
+    max_pending_drips: usize,
+
+    /// Days of claim history kept in the claim store, older claims are pruned hourly (0 keeps everything)
+    #[arg(long, default_value = "365")]
+    history_retention_days: u64,
+
+    /// Origin allowed to call the faucet from a browser, e.g. https://faucet.example.org (repeatable, any origin when unset)
+    #[arg(long = "allowed-origin")]
+    allowed_origins: Vec<String>,
+
+    /// Reject /faucet requests whose Origin or Referer is not an --allowed-origin
+    #[arg(long, requires = "allowed_origins")]
+    require_origin: bool,
+
+    /// Append every rejected /faucet request to this file as JSON lines (also logged under the `rejections` target)
+    #[arg(long)]
+    rejection_log_file: Option<PathBuf>,
+
+    /// Sign /info and /health bodies with the faucet key, sent as an EIP-191 signature in X-Signature
+    #[arg(long)]
+    sign_responses: bool,
+
+    /// Store only salted hashes of recipients, IPs and transaction hashes
+    #[arg(long)]
+    no_store_pii: bool,
+
+    /// Salt for hashed claim records, defaults to one derived from the private key
+    #[arg(long)]
+    pii_salt: Option<String>,
+
+    /// Response schema used when a request has no Accept-Version header
+    #[arg(long, value_enum, default_value = "v1")]
+    response_version: ResponseVersion,
 }
 
 // Request and Response structures
 #[derive(Deserialize)]
 struct FaucetRequest {
     address: String,
+    /// Optional gas price bid, clamped to the operator bounds and paid for out of the amount
+    gas_price_gwei: Option<u64>,
+    /// Opaque client reference echoed back in the response
+    client_ref: Option<String>,
+    /// Name of a configured amount preset to send instead of the default amount
+    preset: Option<String>,
+    /// `false` stores the claim hashed instead of with the raw recipient and IP
+    store: Option<bool>,
+}
+
+impl FaucetRequest {
+    /// Whether the recipient and IP may only be kept hashed, by operator policy or the request's choice.
+    fn pseudonymous(&self, no_store_pii: bool) -> bool {
+        no_store_pii || self.store == Some(false)
+    }
 }
 
 #[derive(Serialize)]
 struct FaucetResponse {
     transaction_hash: String,
+    amount: String,
+    /// Set when the faucet could only afford part of the usual amount
+    reduced: bool,
+    /// USD price per token the amount was computed at, when using the price oracle
+    #[serde(skip_serializing_if = "Option::is_none")]
+    usd_rate: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_ref: Option<String>,
+}
+
+/// Amounts reported by v2 responses, raw and formatted.
+#[derive(Serialize)]
+struct AmountV2 {
+    wei: String,
+    formatted: String,
+}
+
+#[derive(Serialize)]
+struct FaucetResponseV2 {
+    transaction_hash: String,
+    amount: AmountV2,
+    reduced: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    usd_rate: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_ref: Option<String>,
+}
+
+/// A transaction sent by /faucet.
+struct Drip {
+    to: Address,
+    tx_hash: TxHash,
+    nonce: u64,
+    private: bool,
+    amount: U256,
+    reduced: bool,
+    usd_rate: Option<f64>,
+    gas_limit: U256,
+    fees: Fees,
+    calldata: Bytes,
+    /// The claim as stored, possibly pseudonymized
+    claim: Claim,
+    pseudonymous: bool,
+}
+
+impl Drip {
+    fn render(&self, version: ResponseVersion, client_ref: Option<String>) -> HttpResponse {
+        let transaction_hash = format!("{:?}", self.tx_hash);
+        let formatted = format_units(self.amount, NATIVE_DECIMALS);
+        match version {
+            ResponseVersion::V1 => response::ok(
+                version,
+                &FaucetResponse {
+                    transaction_hash,
+                    amount: formatted,
+                    reduced: self.reduced,
+                    usd_rate: self.usd_rate,
+                    client_ref,
+                },
+            ),
+            ResponseVersion::V2 => response::ok(
+                version,
+                &FaucetResponseV2 {
+                    transaction_hash,
+                    amount: AmountV2 {
+                        wei: self.amount.to_string(),
+                        formatted,
+                    },
+                    reduced: self.reduced,
+                    usd_rate: self.usd_rate,
+                    client_ref,
+                },
+            ),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+/// Maximum length in bytes of a request's client_ref
+const MAX_CLIENT_REF_LEN: usize = 128;
+
+/// Seconds clients are asked to wait after the node reports the faucet out of funds
+const INSUFFICIENT_FUNDS_RETRY_SECS: u64 = 300;
+
+/// How often claims older than --history-retention-days are pruned
+const CLAIM_PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How often a pending drip's receipt is polled
+const PENDING_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Times a sent transaction is looked up with --verify-broadcast before it counts as not accepted
+const VERIFY_BROADCAST_ATTEMPTS: u32 = 3;
+
+/// Delay before resending a transaction the node didn't know
+const VERIFY_BROADCAST_DELAY: Duration = Duration::from_millis(500);
+
+/// Seconds clients are asked to wait while the mempool is congested
+const MEMPOOL_RETRY_SECS: u64 = 30;
+
+/// Response header carrying the --sign-responses signature
+const SIGNATURE_HEADER: &str = "x-signature";
+
+/// Wei in one gwei
+const WEI_PER_GWEI: u64 = 1_000_000_000;
+
+/// Decimals of the chain's native token
+const NATIVE_DECIMALS: u8 = 18;
+
+/// Formats a raw token amount as a decimal string scaled by `decimals`, trimming trailing zeros.
+fn format_units(amount: U256, decimals: u8) -> String {
+    let (whole, frac) = amount.div_rem(U256::from(10).pow(U256::from(decimals)));
+    if frac.is_zero() {
+        return whole.to_string();
+    }
+    let frac = format!("{:0>width$}", frac.to_string(), width = decimals as usize);
+    format!("{}.{}", whole, frac.trim_end_matches('0'))
+}
+
+type FaucetProvider = RootProvider<RpcTrace<Http<Client>>>;
+
+// App state structure
+struct AppState {
+    provider: Arc<FaucetProvider>,
+    wallet: EthereumWallet,
+    /// Signs /info and /health bodies when --sign-responses is set
+    response_signer: Option<PrivateKeySigner>,
+    tokens_per_request: U256,
+    /// Amounts selectable with the request's preset field, in the configured order
+    amount_presets: Vec<(String, U256)>,
+    balance: BalanceCache,
+    gas_price: U256,
+    gas_limit: U256,
+    estimate_gas: bool,
+    max_gas_bumps: u32,
+    min_gas_price_gwei: u64,
+    max_gas_price_gwei: u64,
+    priority_fee_floor: Option<PriorityFeeFloor>,
+    max_block_age_secs: Option<u64>,
+    max_mempool_pending: Option<u64>,
+    mempool: MempoolMonitor,
+    send_timeout: Duration,
+    confirmation_timeout: Duration,
+    verify_broadcast: bool,
+    rpc_proxy: Option<RpcProxy>,
+    enable_cost_estimate: bool,
+    known_addresses: HashMap<Address, String>,
+    client_ip: ClientIpResolver,
+    anonymizer_ranges: IpRanges,
+    anonymizer_policy: AnonymizerPolicy,
+    anonymizer_cooldown_secs: u64,
+    min_response_time: Option<Duration>,
+    store: Box<dyn ClaimStore>,
+    cooldown_secs: u64,
+    max_addresses_per_ip: Option<u64>,
+    eligibility: Option<EligibilityService>,
+    eligibility_fail_open: bool,
+    faucet_id: Option<String>,
+    tag_calldata: bool,
+    allow_partial_drip: bool,
+    min_fallback_amount: U256,
+    recipient_prefixes: Vec<String>,
+    price_oracle: Option<PriceOracle>,
+    /// Set once the node is found to reject EIP-1559 transactions
+    legacy_transactions: AtomicBool,
+    /// Set while the wallet can't cover a drip, reported by /health until a drip succeeds
+    low_balance: AtomicBool,
+    in_flight: InFlightLimiter,
+    pending: PendingDrips,
+    explorer: Option<Explorer>,
+    nonces: NonceTracker,
+    rejection_log: RejectionLog,
+    no_store_pii: bool,
+    pseudonymizer: Pseudonymizer,
+    interval_gate: IntervalGate,
+    /// Extra endpoints each transaction is broadcast to, keyed by URL
+    broadcast_targets: Vec<(String, FaucetProvider)>,
+    private_relay: Option<PrivateRelay>,
+    relay_fallback: bool,
+    response_version: ResponseVersion,
+    /// Normalized origins accepted by CORS and --require-origin
+    allowed_origins: Vec<String>,
+    require_origin: bool,
+}
+
+/// Calldata tagging drips with the faucet id so on-chain records can be attributed.
+fn drip_calldata(state: &AppState) -> Bytes {
+    match &state.faucet_id {
+        Some(faucet_id) if state.tag_calldata => Bytes::copy_from_slice(faucet_id.as_bytes()),
+        _ => Bytes::new(),
+    }
+}
+
+/// Intrinsic gas charged for transaction calldata.
+fn calldata_gas(data: &[u8]) -> u64 {
+    data.iter().map(|&b| if b == 0 { 4 } else { 16 }).sum()
+}
+
+/// Parses a `NAME=AMOUNT` preset, the amount in wei or in ether with an `eth` suffix.
+fn parse_amount_preset(preset: &str) -> Result<(String, U256)> {
+    let invalid = || {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!(
+                "Invalid amount preset {}, expected NAME=WEI or NAME=<ether>eth",
+                preset
+            ),
+        )
+    };
+
+    let (name, amount) = preset.split_once('=').ok_or_else(invalid)?;
+    let amount = match amount.trim().strip_suffix("eth") {
+        Some(ether) => alloy_primitives::utils::parse_ether(ether.trim()).map_err(|_| invalid())?,
+        None => U256::from_str_radix(amount.trim(), 10).map_err(|_| invalid())?,
+    };
+    if name.trim().is_empty() || amount.is_zero() {
+        return Err(invalid());
+    }
+    Ok((name.trim().to_string(), amount))
+}
+
+/// Security headers sent on every response unless overridden with --response-header.
+const DEFAULT_RESPONSE_HEADERS: &[(&str, &str)] = &[
+    ("x-content-type-options", "nosniff"),
+    ("x-frame-options", "DENY"),
+    ("referrer-policy", "no-referrer"),
+];
+
+/// Parses `KEY=VALUE` headers on top of the default security headers.
+fn response_headers(custom: &[String]) -> Result<Vec<(HeaderName, HeaderValue)>> {
+    let invalid = |header: &str| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("Invalid response header {}, expected KEY=VALUE", header),
+        )
+    };
+
+    let mut headers = DEFAULT_RESPONSE_HEADERS
+        .iter()
+        .map(|(name, value)| {
+            (
+                HeaderName::from_static(name),
+                HeaderValue::from_static(value),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    for header in custom {
+        let (name, value) = header.split_once('=').ok_or_else(|| invalid(header))?;
+        let name = HeaderName::try_from(name.trim()).map_err(|_| invalid(header))?;
+        let value = HeaderValue::try_from(value.trim()).map_err(|_| invalid(header))?;
+        headers.retain(|(existing, _)| *existing != name);
+        headers.push((name, value));
+    }
+    Ok(headers)
 }
 
-#[derive(Serialize)]
-struct ErrorResponse {
-    error: String,
-}
+/// Loads a `{"category": ["0x...", ...]}` file into a lookup of address to category.
+fn load_known_addresses(path: &Path) -> Result<HashMap<Address, String>> {
+    let invalid = |e: String| std::io::Error::new(std::io::ErrorKind::InvalidData, e);
+
+    let contents = std::fs::read_to_string(path)?;
+    let categories: HashMap<String, Vec<String>> =
+        serde_json::from_str(&contents).map_err(|e| invalid(e.to_string()))?;
+
+    let mut known = HashMap::new();
+    for (category, addresses) in categories {
+        for address in addresses {
+            let address = address
+                .parse::<Address>()
+                .map_err(|e| invalid(format!("Invalid known address {}: {}", address, e)))?;
+            known.insert(address, category.clone());
+        }
+    }
+    Ok(known)
+}
+
+/// Upper bound for the delay between RPC connection attempts at startup.
+const MAX_RPC_WAIT_BACKOFF: Duration = Duration::from_secs(10);
+
+/// Retries `get_chain_id` with exponential backoff until the node answers or `timeout` elapses.
+async fn wait_for_rpc(provider: &FaucetProvider, rpc_url: &str, timeout: Duration) -> Result<u64> {
+    let started = Instant::now();
+    let mut delay = Duration::from_millis(500);
+    let mut attempt = 1;
+    loop {
+        match provider.get_chain_id().await {
+            Ok(chain_id) => return Ok(chain_id),
+            Err(e) if started.elapsed() + delay > timeout => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    format!(
+                        "RPC at {} still unreachable after {}s: {}",
+                        rpc_url,
+                        timeout.as_secs(),
+                        e
+                    ),
+                ))
+            }
+            Err(e) => {
+                warn!(
+                    "RPC at {} unreachable (attempt {}): {}. Retrying in {:?}",
+                    rpc_url, attempt, e, delay
+                );
+                actix_web::rt::time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_RPC_WAIT_BACKOFF);
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Clears the claim store if the node now serves a different chain than the stored claims were made on.
+async fn reset_on_chain_change(provider: &FaucetProvider, store: &dyn ClaimStore) -> Result<()> {
+    let rpc_error = |e: String| std::io::Error::other(format!("Failed to identify chain: {}", e));
+    let store_error = |e: sqlx::Error| std::io::Error::other(e.to_string());
+
+    let chain_id = provider
+        .get_chain_id()
+        .await
+        .map_err(|e| rpc_error(e.to_string()))?;
+    let genesis = provider
+        .get_block_by_number(BlockNumberOrTag::Earliest, false)
+        .await
+        .map_err(|e| rpc_error(e.to_string()))?
+        .ok_or_else(|| rpc_error("genesis block not found".to_string()))?;
+    let identity = format!("{}:{:?}", chain_id, genesis.header.hash);
+
+    match store.chain_identity().await.map_err(store_error)? {
+        Some(previous) if previous != identity => {
+            let cleared = store.clear().await.map_err(store_error)?;
+            warn!(
+                "CHAIN RESET DETECTED: chain changed from {} to {}, cleared {} claims and all cooldowns",
+                previous, identity, cleared
+            );
+        }
+        _ => {}
+    }
+    store
+        .set_chain_identity(&identity)
+        .await
+        .map_err(store_error)
+}
+
+/// Seconds elapsed since the latest block was produced, according to its header timestamp.
+async fn latest_block_age(provider: &FaucetProvider) -> std::result::Result<u64, String> {
+    let block = provider
+        .get_block_by_number(BlockNumberOrTag::Latest, false)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "latest block not found".to_string())?;
+    let now = chrono::Utc::now().timestamp().max(0) as u64;
+    Ok(now.saturating_sub(block.header.timestamp))
+}
+
+enum SendError {
+    /// The transaction could not be built or signed
+    Build(String),
+    /// The node rejected the transaction or could not be reached
+    Rpc(TransportError),
+    /// Broadcasting did not finish within --send-timeout-secs, the node may still have the transaction
+    Timeout(TxHash),
+    /// The private relay rejected the transaction or could not be reached
+    Relay(String),
+    /// The node returned a hash but never knew the transaction, with --verify-broadcast
+    NotAccepted(TxHash),
+}
+
+impl std::fmt::Display for SendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SendError::Build(e) | SendError::Relay(e) => f.write_str(e),
+            SendError::Rpc(e) => write!(f, "{}", e),
+            SendError::Timeout(tx_hash) => write!(f, "timed out broadcasting {:?}", tx_hash),
+            SendError::NotAccepted(tx_hash) => {
+                write!(f, "node did not accept transaction {:?}", tx_hash)
+            }
+        }
+    }
+}
+
+/// A transaction accepted for inclusion.
+struct Sent {
+    tx_hash: TxHash,
+    /// Submitted through the private relay, so it won't show up in the public mempool
+    private: bool,
+}
+
+/// Fees per gas of a drip. Legacy transactions pay `max_fee` as their gas price.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Fees {
+    /// Most the drip pays per gas, what balance checks are made against
+    max_fee: U256,
+    priority_fee: U256,
+}
+
+impl Fees {
+    /// Fees paying `gas_price` whatever the base fee.
+    fn flat(gas_price: U256) -> Self {
+        Self {
+            max_fee: gas_price,
+            priority_fee: gas_price,
+        }
+    }
+}
+
+/// Raises the priority fee to what recent blocks paid so a drip isn't left unmined, at the
+/// faucet's expense.
+async fn with_fee_floor(state: &AppState, gas_price: U256) -> Fees {
+    match &state.priority_fee_floor {
+        Some(floor) if !state.legacy_transactions.load(Ordering::Relaxed) => {
+            match floor.floor(&state.provider).await {
+                Ok(recent) => {
+                    let max_gas_price =
+                        U256::from(state.max_gas_price_gwei) * U256::from(WEI_PER_GWEI);
+                    floored_fees(gas_price, recent, max_gas_price)
+                }
+                Err(e) => {
+                    warn!("fee history unavailable, using configured gas price: {}", e);
+                    Fees::flat(gas_price)
+                }
+            }
+        }
+        _ => Fees::flat(gas_price),
+    }
+}
+
+/// Priority fee of at least the recent floor, with a max fee that survives the base fee doubling,
+/// both capped at `max_gas_price`.
+fn floored_fees(gas_price: U256, recent: RecentFees, max_gas_price: U256) -> Fees {
+    let priority_fee = gas_price.max(U256::from(recent.priority_fee).min(max_gas_price));
+    let max_fee = (U256::from(recent.base_fee) * U256::from(2) + priority_fee)
+        .min(max_gas_price)
+        .max(priority_fee);
+    Fees {
+        max_fee,
+        priority_fee,
+    }
+}
+
+/// Sets EIP-1559 or legacy fee fields on `tx`.
+fn with_fees(tx: TransactionRequest, fees: Fees, legacy: bool) -> TransactionRequest {
+    if legacy {
+        tx.with_gas_price(fees.max_fee.to())
+    } else {
+        tx.max_fee_per_gas(fees.max_fee.to())
+            .max_priority_fee_per_gas(fees.priority_fee.to())
+    }
+}
+
+/// Whether the node rejected a transaction because it doesn't support its type.
+fn is_unsupported_tx_type(error: &TransportError) -> bool {
+    let message = error.to_string().to_lowercase();
+    [
+        "transaction type not supported",
+        "unsupported transaction type",
+        "tx type not supported",
+    ]
+    .iter()
+    .any(|pattern| message.contains(pattern))
+}
+
+/// Whether the node rejected a transaction because the faucet can't cover its value and gas.
+fn is_insufficient_funds(error: &TransportError) -> bool {
+    error
+        .to_string()
+        .to_lowercase()
+        .contains("insufficient funds")
+}
+
+/// Whether a node rejected a transaction because it already has it in its mempool.
+fn is_already_known(error: &TransportError) -> bool {
+    let message = error.to_string().to_lowercase();
+    ["already known", "already imported", "known transaction"]
+        .iter()
+        .any(|pattern| message.contains(pattern))
+}
+
+/// Signs `tx` with the faucet wallet and broadcasts it.
+async fn sign_and_send(
+    state: &AppState,
+    tx: TransactionRequest,
+) -> std::result::Result<Sent, SendError> {
+    let envelope = tx
+        .build(&state.wallet)
+        .await
+        .map_err(|e| SendError::Build(e.to_string()))?;
+    let tx_hash = *envelope.tx_hash();
+    let send = async {
+        if let Some(relay) = &state.private_relay {
+            match relay.send(tx_hash, &envelope.encoded_2718().into()).await {
+                Ok(hash) => {
+                    if hash != tx_hash {
+                        warn!("private relay reported hash {:?} for {:?}", hash, tx_hash);
+                    }
+                    return Ok(Sent {
+                        tx_hash,
+                        private: true,
+                    });
+                }
+                Err(e) if state.relay_fallback => {
+                    warn!(
+                        "private relay failed for {:?}, broadcasting publicly: {}",
+                        tx_hash, e
+                    );
+                }
+                Err(e) => return Err(SendError::Relay(e)),
+            }
+        }
+
+        let raw: Bytes = envelope.encoded_2718().into();
+        let tx_hash = if !state.broadcast_targets.is_empty() {
+            broadcast_to_all(state, tx_hash, raw.clone()).await?
+        } else {
+            let pending = state
+                .provider
+                .send_tx_envelope(envelope)
+                .await
+                .map_err(SendError::Rpc)?;
+            *pending.tx_hash()
+        };
+        if state.verify_broadcast {
+            verify_broadcast(state, tx_hash, &raw).await?;
+        }
+        Ok(Sent {
+            tx_hash,
+            private: false,
+        })
+    };
+    actix_web::rt::time::timeout(state.send_timeout, send)
+        .await
+        .unwrap_or(Err(SendError::Timeout(tx_hash)))
+}
+
+/// Confirms the node knows a transaction it returned a hash for, resending it while it doesn't.
+async fn verify_broadcast(
+    state: &AppState,
+    tx_hash: TxHash,
+    raw: &Bytes,
+) -> std::result::Result<(), SendError> {
+    for attempt in 1..=VERIFY_BROADCAST_ATTEMPTS {
+        // Only presence matters, so don't depend on the node's transaction format
+        let known: std::result::Result<Option<serde_json::Value>, _> = state
+            .provider
+            .raw_request("eth_getTransactionByHash".into(), (tx_hash,))
+            .await;
+        match known {
+            Ok(Some(_)) => return Ok(()),
+            Ok(None) if attempt == VERIFY_BROADCAST_ATTEMPTS => break,
+            Ok(None) => warn!(
+                "node returned hash {:?} but does not know the transaction, resending (attempt {})",
+                tx_hash, attempt
+            ),
+            Err(e) => {
+                warn!("could not verify broadcast of {:?}: {}", tx_hash, e);
+                return Ok(());
+            }
+        }
+
+        actix_web::rt::time::sleep(VERIFY_BROADCAST_DELAY).await;
+        match state.provider.send_raw_transaction(raw).await {
+            Ok(_) => {}
+            Err(e) if is_already_known(&e) => return Ok(()),
+            Err(e) => return Err(SendError::Rpc(e)),
+        }
+    }
+    Err(SendError::NotAccepted(tx_hash))
+}
+
+/// Signs and sends a transfer using `nonce`, switching to legacy transactions for good if the node
+/// rejects EIP-1559.
+async fn send_transfer(
+    state: &AppState,
+    tx: TransactionRequest,
+    fees: Fees,
+    nonce: u64,
+) -> std::result::Result<Sent, SendError> {
+    let legacy = state.legacy_transactions.load(Ordering::Relaxed);
+    let mut result = sign_and_send(state, with_fees(tx.clone(), fees, legacy)).await;
+    if !legacy && matches!(&result, Err(SendError::Rpc(e)) if is_unsupported_tx_type(e)) {
+        warn!("node does not support EIP-1559 transactions, downgrading to legacy transactions");
+        state.legacy_transactions.store(true, Ordering::Relaxed);
+        result = sign_and_send(state, with_fees(tx, fees, true)).await;
+    }
+
+    // A timed out broadcast went through if the node has moved past its nonce
+    if let Err(SendError::Timeout(tx_hash)) = result {
+        let from_address = state.wallet.default_signer().address();
+        if let Ok(count) = state
+            .provider
+            .get_transaction_count(from_address)
+            .pending()
+            .await
+        {
+            if count > nonce {
+                warn!(
+                    "broadcast of {:?} timed out but the node accepted it",
+                    tx_hash
+                );
+                result = Ok(Sent {
+                    tx_hash,
+                    private: false,
+                });
+            }
+        }
+    }
+    result
+}
+
+/// Sends a signed transaction to the primary RPC and every broadcast target concurrently,
+/// succeeding as soon as one node accepts it. The remaining sends finish in the background.
+async fn broadcast_to_all(
+    state: &AppState,
+    tx_hash: TxHash,
+    raw: Bytes,
+) -> std::result::Result<TxHash, SendError> {
+    let targets = std::iter::once(("primary RPC".to_string(), (*state.provider).clone()))
+        .chain(state.broadcast_targets.iter().cloned());
+    let mut sends: FuturesUnordered<_> = targets
+        .map(|(url, provider)| {
+            let raw = raw.clone();
+            async move {
+                let result = match provider.send_raw_transaction(&raw).await {
+                    Ok(pending) => Ok(*pending.tx_hash()),
+                    Err(e) if is_already_known(&e) => Ok(tx_hash),
+                    Err(e) => Err(e),
+                };
+                (url, result)
+            }
+        })
+        .collect();
+
+    let mut first_error = None;
+    while let Some((url, result)) = sends.next().await {
+        match result {
+            Ok(hash) => {
+                if hash != tx_hash {
+                    warn!("{} reported hash {:?} for {:?}", url, hash, tx_hash);
+                }
+                actix_web::rt::spawn(async move {
+                    while let Some((url, result)) = sends.next().await {
+                        if let Err(e) = result {
+                            warn!("broadcast of {:?} to {} failed: {}", tx_hash, url, e);
+                        }
+                    }
+                });
+                return Ok(tx_hash);
+            }
+            Err(e) => {
+                warn!("broadcast of {:?} to {} failed: {}", tx_hash, url, e);
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    Err(SendError::Rpc(
+        first_error.expect("at least one broadcast target"),
+    ))
+}
+
+/// The `scheme://host[:port]` origin of a URL.
+fn url_origin(url: &str) -> Option<String> {
+    let origin = reqwest::Url::parse(url).ok()?.origin();
+    origin.is_tuple().then(|| origin.ascii_serialization())
+}
+
+/// With --require-origin, only browsers on an allowed origin may request tokens.
+fn check_origin(req: &HttpRequest, state: &AppState) -> std::result::Result<(), FaucetError> {
+    if !state.require_origin {
+        return Ok(());
+    }
+
+    let header = |name| req.headers().get(name).and_then(|v| v.to_str().ok());
+    let origin = header(header::ORIGIN)
+        .map(str::to_string)
+        .or_else(|| header(header::REFERER).and_then(url_origin));
+    match origin {
+        Some(origin) if state.allowed_origins.contains(&origin) => Ok(()),
+        _ => Err(FaucetError::new(
+            StatusCode::FORBIDDEN,
+            "ORIGIN_NOT_ALLOWED",
+            "Requests must come from an allowed origin",
+        )),
+    }
+}
+
+/// Explains rejected /faucet bodies, calling out the common case of forgetting to send one.
+fn json_error(err: JsonPayloadError, req: &HttpRequest) -> actix_web::Error {
+    let default_version = req
+        .app_data::<web::Data<AppState>>()
+        .map_or(ResponseVersion::V1, |state| state.response_version);
+    let version = ResponseVersion::from_request(req, default_version).unwrap_or(default_version);
+
+    let empty = match req.headers().get(header::CONTENT_LENGTH) {
+        Some(length) => length.as_bytes() == b"0",
+        None => !req.headers().contains_key(header::TRANSFER_ENCODING),
+    };
+    let error = if empty {
+        FaucetError::new(
+            StatusCode::BAD_REQUEST,
+            "MISSING_BODY",
+            r#"Request body is required, send JSON like {"address": "0x..."} with Content-Type: application/json"#,
+        )
+    } else {
+        FaucetError::new(
+            StatusCode::BAD_REQUEST,
+            "INVALID_BODY",
+            format!("Invalid request body: {}", err),
+        )
+    };
+    InternalError::from_response(err, error.render(version)).into()
+}
+
+/// Outcome of a sent drip as seen by the node, or the block explorer when the node can't tell.
+enum DripStatus {
+    Pending,
+    Mined {
+        success: bool,
+        block: Option<u64>,
+        gas_used: Option<u128>,
+    },
+    Dropped,
+}
+
+/// Status of a drip the node has a receipt for. A mined drip may still have reverted.
+fn receipt_status(receipt: &TransactionReceipt) -> DripStatus {
+    DripStatus::Mined {
+        success: receipt.status(),
+        block: receipt.block_number,
+        gas_used: Some(receipt.gas_used),
+    }
+}
+
+async fn drip_status(state: &AppState, tx_hash: TxHash, private: bool) -> DripStatus {
+    let from_explorer = |status: Option<bool>| match status {
+        Some(success) => DripStatus::Mined {
+            success,
+            block: None,
+            gas_used: None,
+        },
+        None => DripStatus::Pending,
+    };
+
+    match state.provider.get_transaction_receipt(tx_hash).await {
+        Ok(Some(receipt)) => receipt_status(&receipt),
+        // Private transactions only become visible once mined, so absence doesn't mean dropped
+        Ok(None) if private => DripStatus::Pending,
+        Ok(None) => match state.provider.get_transaction_by_hash(tx_hash).await {
+            // The RPC may lag behind, so cross-check before concluding it was dropped
+            Ok(None) => match &state.explorer {
+                Some(explorer) => match explorer.receipt_status(tx_hash).await {
+                    Ok(Some(success)) => DripStatus::Mined {
+                        success,
+                        block: None,
+                        gas_used: None,
+                    },
+                    Ok(None) => DripStatus::Dropped,
+                    Err(e) => {
+                        warn!("explorer lookup for {:?} failed: {}", tx_hash, e);
+                        DripStatus::Dropped
+                    }
+                },
+                None => DripStatus::Dropped,
+            },
+            _ => DripStatus::Pending,
+        },
+        Err(e) => {
+            warn!("failed to check drip {:?}: {}", tx_hash, e);
+            match &state.explorer {
+                Some(explorer) => match explorer.receipt_status(tx_hash).await {
+                    Ok(status) => from_explorer(status),
+                    Err(e) => {
+                        warn!("explorer lookup for {:?} failed: {}", tx_hash, e);
+                        DripStatus::Pending
+                    }
+                },
+                None => DripStatus::Pending,
+            }
+        }
+    }
+}
+
+/// Doubled gas limit for resending a reverted drip that used all of `gas_limit`, while fewer than
+/// `max_bumps` resends were made. A drip that reverted for another reason isn't resent.
+fn bumped_gas_limit(
+    gas_used: Option<u128>,
+    gas_limit: U256,
+    bumps: u32,
+    max_bumps: u32,
+) -> Option<U256> {
+    let out_of_gas = gas_used.is_some_and(|used| U256::from(used) >= gas_limit);
+    (out_of_gas && bumps < max_bumps).then(|| gas_limit * U256::from(2))
+}
+
+/// Resends a drip that ran out of gas with `gas_limit`, using a fresh nonce returned alongside it.
+async fn resend_drip(
+    state: &AppState,
+    drip: &Drip,
+    gas_limit: U256,
+) -> std::result::Result<(Sent, u64), String> {
+    let from_address = state.wallet.default_signer().address();
+    let node_pending = state
+        .provider
+        .get_transaction_count(from_address)
+        .pending()
+        .await
+        .map_err(|e| e.to_string())?;
+    let nonce = state.nonces.reserve(node_pending);
+    let chain_id = state
+        .provider
+        .get_chain_id()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut tx = TransactionRequest::default()
+        .to(drip.to)
+        .nonce(nonce.nonce)
+        .value(drip.amount)
+        .gas_limit(gas_limit.to::<u64>());
+    tx.set_chain_id(chain_id);
+    if !drip.calldata.is_empty() {
+        tx.set_input(drip.calldata.clone());
+    }
+
+    let sent = send_transfer(state, tx, drip.fees, nonce.nonce)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok((sent, nonce.commit()))
+}
+
+/// Releases a recipient's pending drip once it is mined, dropped from the mempool, or times out.
+/// A drip that mined but reverted is un-recorded so it neither counts nor starts a cooldown,
+/// unless it ran out of gas and --max-gas-bumps allows resending it with a higher limit.
+async fn watch_pending(state: web::Data<AppState>, mut drip: Drip) {
+    let mut started = Instant::now();
+    let mut bumps = 0;
+    while started.elapsed() < state.confirmation_timeout {
+        actix_web::rt::time::sleep(PENDING_POLL_INTERVAL).await;
+        let (to, tx_hash) = (drip.to, drip.tx_hash);
+        match drip_status(&state, tx_hash, drip.private).await {
+            DripStatus::Pending => continue,
+            DripStatus::Mined { success: true, .. } => {}
+            DripStatus::Mined {
+                success: false,
+                block,
+                gas_used,
+            } => {
+                warn!(
+                    "drip {:?} to {:?} reverted in block {}",
+                    tx_hash,
+                    to,
+                    block.map_or_else(|| "unknown".to_string(), |b| b.to_string())
+                );
+                // The claim may have been stored under either form of the hash
+                for stored in [
+                    format!("{:?}", tx_hash),
+                    state.pseudonymizer.tx_hash(tx_hash),
+                ] {
+                    if let Err(e) = state.store.remove_claim(&stored).await {
+                        warn!("failed to remove claim for {:?}: {}", tx_hash, e);
+                    }
+                }
+
+                if let Some(gas_limit) =
+                    bumped_gas_limit(gas_used, drip.gas_limit, bumps, state.max_gas_bumps)
+                {
+                    warn!(
+                        "drip {:?} to {:?} ran out of gas, resending with gas limit {} instead of {}",
+                        tx_hash, to, gas_limit, drip.gas_limit
+                    );
+                    match resend_drip(&state, &drip, gas_limit).await {
+                        Ok((sent, nonce)) => {
+                            bumps += 1;
+                            started = Instant::now();
+                            state.balance.invalidate();
+                            state.pending.resubmitted(to, sent.tx_hash);
+                            drip.tx_hash = sent.tx_hash;
+                            drip.nonce = nonce;
+                            drip.private = sent.private;
+                            drip.gas_limit = gas_limit;
+                            drip.claim.tx_hash = if drip.pseudonymous {
+                                state.pseudonymizer.tx_hash(sent.tx_hash)
+                            } else {
+                                format!("{:?}", sent.tx_hash)
+                            };
+                            if let Err(e) = state.store.record_claim(&drip.claim).await {
+                                warn!("failed to record claim for {:?}: {}", to, e);
+                            }
+                            continue;
+                        }
+                        Err(e) => warn!("failed to resend drip to {:?}: {}", to, e),
+                    }
+                }
+
+                let recipient = (!drip.pseudonymous).then(|| to.to_string());
+                state
+                    .rejection_log
+                    .record("TRANSACTION_REVERTED", recipient.as_deref(), None);
+            }
+            DripStatus::Dropped => {
+                warn!("drip {:?} to {:?} was dropped", tx_hash, to);
+                state.nonces.dropped(drip.nonce);
+            }
+        }
+        break;
+    }
+    state.pending.clear(drip.to);
+}
+
+/// Serializes `body`, adding an X-Signature header over the exact bytes sent with --sign-responses.
+fn signed_json(
+    state: &AppState,
+    mut response: HttpResponseBuilder,
+    body: &serde_json::Value,
+) -> HttpResponse {
+    let body = body.to_string();
+    if let Some(signer) = &state.response_signer {
+        match signer.sign_message_sync(body.as_bytes()) {
+            Ok(signature) => {
+                response.insert_header((
+                    SIGNATURE_HEADER,
+                    format!("0x{}", hex::encode(signature.as_bytes())),
+                ));
+            }
+            Err(e) => warn!("failed to sign response: {}", e),
+        }
+    }
+    response.content_type(ContentType::json()).body(body)
+}
+
+/// Length of `retention_days` in seconds, `None` if it doesn't fit a timestamp.
+fn retention_secs(retention_days: u64) -> Option<i64> {
+    retention_days
+        .checked_mul(24 * 60 * 60)
+        .and_then(|secs| i64::try_from(secs).ok())
+}
+
+/// Periodically deletes claims older than `retention_days` (`retention_secs` long) from the
+/// claim store.
+async fn prune_claims(state: web::Data<AppState>, retention_days: u64, retention_secs: i64) {
+    let mut interval = actix_web::rt::time::interval(CLAIM_PRUNE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let cutoff = chrono::Utc::now()
+            .timestamp()
+            .saturating_sub(retention_secs);
+        match state.store.prune_before(cutoff).await {
+            Ok(0) => {}
+            Ok(pruned) => info!(
+                "pruned {} claims older than {} days",
+                pruned, retention_days
+            ),
+            Err(e) => warn!("failed to prune claim history: {}", e),
+        }
+    }
+}
+
+/// Records a rejected request, leaving out the recipient and IP when they may only be stored hashed.
+fn log_rejection(
+    log: &RejectionLog,
+    code: &str,
+    data: &FaucetRequest,
+    client_ip: Option<IpAddr>,
+    no_store_pii: bool,
+) {
+    if data.pseudonymous(no_store_pii) {
+        log.record(code, None, None);
+    } else {
+        log.record(code, Some(&data.address), client_ip);
+    }
+}
+
+async fn send_tokens(
+    req: HttpRequest,
+    data: web::Json<FaucetRequest>,
+    state: web::Data<AppState>,
+) -> HttpResponse {
+    let client_ip = state.client_ip.resolve(&req);
+    match ResponseVersion::from_request(&req, state.response_version) {
+        Ok(version) => {
+            let result = match (check_origin(&req, &state), client_ip) {
+                (Err(e), _) => Err(e),
+                (Ok(()), Some(ip)) => match state.in_flight.try_acquire(ip) {
+                    Some(_guard) => dispense(&data, client_ip, &state).await,
+                    None => Err(FaucetError::new(
+                        StatusCode::TOO_MANY_REQUESTS,
+                        "TOO_MANY_CONCURRENT_REQUESTS",
+                        "Too many concurrent requests from this IP",
+                    )),
+                },
+                (Ok(()), None) => dispense(&data, client_ip, &state).await,
+            };
+            match result {
+                Ok(drip) => {
+                    let response = drip.render(version, data.client_ref.clone());
+                    actix_web::rt::spawn(watch_pending(state.clone(), drip));
+                    response
+                }
+                Err(e) => {
+                    log_rejection(
+                        &state.rejection_log,
+                        e.code,
+                        &data,
+                        client_ip,
+                        state.no_store_pii,
+                    );
+                    e.render(version)
+                }
+            }
+        }
+        Err(e) => e.render(state.response_version),
+    }
+}
+
+/// The /faucet route, padded as a whole so extractor errors take as long as any other response.
+fn faucet_resource() -> impl HttpServiceFactory {
+    web::resource("/faucet")
+        .wrap(middleware::from_fn(pad_response_time))
+        .app_data(web::JsonConfig::default().error_handler(json_error))
+        .route(web::post().to(send_tokens))
+}
+
+/// Pads /faucet responses up to the configured minimum duration so rejections can't be told apart
+/// by latency, including bodies rejected before the handler runs.
+async fn pad_response_time(
+    req: ServiceRequest,
+    next: middleware::Next<impl MessageBody>,
+) -> std::result::Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let started = Instant::now();
+    let min_response_time = req
+        .app_data::<web::Data<AppState>>()
+        .and_then(|state| state.min_response_time);
+    let response = next.call(req).await;
+
+    if let Some(min_response_time) = min_response_time {
+        if let Some(remaining) = min_response_time.checked_sub(started.elapsed()) {
+            actix_web::rt::time::sleep(remaining).await;
+        }
+    }
+
+    response
+}
+
+/// Forms `address` may be stored under, hashed and, unless --no-store-pii forbids looking it up, raw.
+fn claim_keys(state: &AppState, address: Address) -> Vec<Address> {
+    let hashed = state.pseudonymizer.address(address);
+    if state.no_store_pii {
+        vec![hashed]
+    } else {
+        vec![hashed, address]
+    }
+}
+
+/// Most recent claim for `address`, whether it was stored raw or hashed.
+async fn last_claim_at(state: &AppState, address: Address) -> sqlx::Result<Option<i64>> {
+    let mut last = None;
+    for key in claim_keys(state, address) {
+        last = last.max(state.store.last_claim_at(key).await?);
+    }
+    Ok(last)
+}
+
+/// Addresses funded from `ip` across raw and hashed claims.
+async fn addresses_funded_from(state: &AppState, ip: IpAddr, except: Address) -> sqlx::Result<u64> {
+    let pseudonymizer = &state.pseudonymizer;
+    let hashed = state
+        .store
+        .addresses_funded_from(pseudonymizer.ip(ip), pseudonymizer.address(except))
+        .await?;
+    if state.no_store_pii {
+        return Ok(hashed);
+    }
+    Ok(state.store.addresses_funded_from(ip, except).await? + hashed)
+}
+
+/// Raises the low balance alert and rejects a drip the wallet can't cover, a faucet-side condition.
+fn insufficient_balance(state: &AppState, amount: U256, to: Address, reason: &str) -> FaucetError {
+    state.low_balance.store(true, Ordering::Relaxed);
+    error!(
+        "LOW BALANCE: cannot send {} to {:?}, the wallet needs a top up: {}",
+        format_units(amount, NATIVE_DECIMALS),
+        to,
+        reason
+    );
+    FaucetError::new(
+        StatusCode::SERVICE_UNAVAILABLE,
+        "INSUFFICIENT_FAUCET_BALANCE",
+        "Faucet balance is too low to cover this drip, try again later",
+    )
+    .with_retry_after(INSUFFICIENT_FUNDS_RETRY_SECS)
+}
+
+/// Balance of the address receiving tokens must be zero. Balance of the sender must be greater than the tokens requested.
+async fn dispense(
+    data: &FaucetRequest,
+    client_ip: Option<IpAddr>,
+    state: &AppState,
+) -> std::result::Result<Drip, FaucetError> {
+    let to_address = match Address::parse_checksummed(&data.address, None) {
+        Ok(addr) => addr,
+        Err(_) => {
+            return Err(FaucetError::new(
+                StatusCode::BAD_REQUEST,
+                "INVALID_ADDRESS",
+                "Invalid address",
+            ))
+        }
+    };
+
+    if let Some(client_ref) = &data.client_ref {
+        if client_ref.len() > MAX_CLIENT_REF_LEN || client_ref.chars().any(char::is_control) {
+            return Err(FaucetError::new(
+                StatusCode::BAD_REQUEST,
+                "INVALID_CLIENT_REF",
+                format!(
+                    "client_ref must be at most {} bytes without control characters",
+                    MAX_CLIENT_REF_LEN
+                ),
+            ));
+        }
+    }
+
+    if !state.recipient_prefixes.is_empty() {
+        let address = hex::encode(to_address);
+        if !state
+            .recipient_prefixes
+            .iter()
+            .any(|prefix| address.starts_with(prefix))
+        {
+            return Err(FaucetError::new(
+                StatusCode::BAD_REQUEST,
+                "RECIPIENT_NOT_ALLOWED",
+                "Receiver does not match an allowed address prefix",
+            ));
+        }
+    }
+
+    if let Some(category) = state.known_addresses.get(&to_address) {
+        return Err(FaucetError::new(
+            StatusCode::BAD_REQUEST,
+            "KNOWN_ADDRESS",
+            format!(
+                "Receiver is a known {} address and cannot be funded",
+                category
+            ),
+        ));
+    }
+
+    // Requests through anonymizing networks are refused or held to a longer cooldown
+    let mut cooldown_secs = state.cooldown_secs;
+    if client_ip.is_some_and(|ip| state.anonymizer_ranges.contains(ip)) {
+        match state.anonymizer_policy {
+            AnonymizerPolicy::Block => {
+                return Err(FaucetError::new(
+                    StatusCode::FORBIDDEN,
+                    "ANONYMIZER_BLOCKED",
+                    "Requests from anonymizing networks are not accepted",
+                ))
+            }
+            AnonymizerPolicy::TighterLimit => {
+                cooldown_secs = cooldown_secs.max(state.anonymizer_cooldown_secs)
+            }
+        }
+    }
+
+    // Only one drip per recipient until the previous one confirms
+    let pending = match state.pending.try_reserve(to_address) {
+        Ok(reservation) => reservation,
+        Err(ReserveError::Pending(tx_hash)) => {
+            return Err(match tx_hash {
+                Some(tx_hash) => FaucetError::new(
+                    StatusCode::CONFLICT,
+                    "DRIP_PENDING",
+                    format!("A drip to this address is pending: {:?}", tx_hash),
+                )
+                .with_transaction_hash(format!("{:?}", tx_hash)),
+                None => FaucetError::new(
+                    StatusCode::CONFLICT,
+                    "DRIP_PENDING",
+                    "A drip to this address is already being sent",
+                ),
+            });
+        }
+        // So many unconfirmed drips means transactions aren't being mined
+        Err(ReserveError::Full) => {
+            return Err(FaucetError::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "TOO_MANY_PENDING",
+                "Too many drips are awaiting confirmation, try again later",
+            ))
+        }
+    };
+
+    if cooldown_secs > 0 {
+        match last_claim_at(state, to_address).await {
+            Ok(Some(last_claim)) => {
+                let remaining = last_claim + cooldown_secs as i64 - chrono::Utc::now().timestamp();
+                if remaining > 0 {
+                    return Err(FaucetError::new(
+                        StatusCode::TOO_MANY_REQUESTS,
+                        "COOLDOWN",
+                        format!("Receiver is on cooldown, retry in {}s", remaining),
+                    ));
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                return Err(FaucetError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "STORE_ERROR",
+                    format!("Failed to check cooldown: {}", e),
+                ))
+            }
+        }
+    }
+
+    if let (Some(max), Some(ip)) = (state.max_addresses_per_ip, client_ip) {
+        match addresses_funded_from(state, ip, to_address).await {
+            Ok(funded) if funded >= max => {
+                return Err(FaucetError::new(
+                    StatusCode::FORBIDDEN,
+                    "ADDRESS_LIMIT_REACHED",
+                    "This IP has already funded the maximum number of addresses",
+                ))
+            }
+            Ok(_) => {}
+            Err(e) => {
+                return Err(FaucetError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "STORE_ERROR",
+                    format!("Failed to check addresses funded from this IP: {}", e),
+                ))
+            }
+        }
+    }
+
+    if let Some(eligibility) = &state.eligibility {
+        match eligibility
+            .is_allowed(to_address, client_ip, data.client_ref.as_deref())
+            .await
+        {
+            Ok(true) => {}
+            Ok(false) => {
+                return Err(FaucetError::new(
+                    StatusCode::FORBIDDEN,
+                    "NOT_ELIGIBLE",
+                    "Receiver is not eligible for this faucet",
+                ))
+            }
+            Err(e) if state.eligibility_fail_open => {
+                warn!(
+                    "eligibility service unavailable, allowing {:?}: {}",
+                    to_address, e
+                );
+            }
+            Err(e) => {
+                warn!("eligibility service unavailable: {}", e);
+                return Err(FaucetError::new(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "ELIGIBILITY_UNAVAILABLE",
+                    "Eligibility could not be checked, try again later",
+                ));
+            }
+        }
+    }
+
+    // Refuse to dispense while the chain is stalled, the transaction would never be mined
+    if let Some(max_age) = state.max_block_age_secs {
+        match latest_block_age(&state.provider).await {
+            Ok(age) if age > max_age => {
+                return Err(FaucetError::new(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "CHAIN_STALLED",
+                    format!("Chain appears stalled: latest block is {}s old", age),
+                ))
+            }
+            Ok(_) => {}
+            Err(e) => {
+                return Err(FaucetError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "RPC_ERROR",
+                    format!("Failed to get latest block: {}", e),
+                ))
+            }
+        }
+    }
+
+    // Don't add to a congested mempool, the drip wouldn't be mined promptly
+    if let Some(max_pending) = state.max_mempool_pending {
+        match state.mempool.pending(&state.provider).await {
+            Ok(pending) if pending > max_pending => {
+                return Err(FaucetError::new(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "MEMPOOL_CONGESTED",
+                    format!("Network is congested with {} pending transactions", pending),
+                )
+                .with_retry_after(MEMPOOL_RETRY_SECS))
+            }
+            Ok(_) => {}
+            Err(e) => warn!("failed to get txpool status, not gating on it: {}", e),
+        }
+    }
 
-// App state structure
-struct AppState {
-    provider: Arc<RootProvider<Http<Client>>>,
-    wallet: EthereumWallet,
-    tokens_per_request: U256,
-    gas_price: U256,
-    gas_limit: U256,
-}
+    // A higher gas price bid is paid for out of the dispensed amount
+    let gas_price = bid_gas_price(state, data.gas_price_gwei);
+    let calldata = drip_calldata(state);
+    let gas_limit = state.gas_limit + U256::from(calldata_gas(&calldata));
 
-/// Balance of the address receiving tokens must be zero. Balance of the sender must be greater than the tokens requested.
-async fn send_tokens(data: web::Json<FaucetRequest>, state: web::Data<AppState>) -> impl Responder {
-    let to_address = match Address::parse_checksummed(&data.address, None) {
-        Ok(addr) => addr,
-        Err(_) => {
-            return HttpResponse::BadRequest().json(ErrorResponse {
-                error: "Invalid address".to_string(),
-            })
+    // Dispense the chosen preset, or a fixed USD value at the current price, falling back to the fixed amount
+    let preset = match &data.preset {
+        Some(name) => match state
+            .amount_presets
+            .iter()
+            .find(|(preset, _)| preset == name)
+        {
+            Some((_, amount)) => Some(*amount),
+            None => {
+                return Err(FaucetError::new(
+                    StatusCode::BAD_REQUEST,
+                    "UNKNOWN_PRESET",
+                    format!("Unknown amount preset {}", name),
+                ))
+            }
+        },
+        None => None,
+    };
+    let (base_amount, usd_rate) = match (preset, &state.price_oracle) {
+        (Some(amount), _) => (amount, None),
+        (None, Some(oracle)) => match oracle.price().await {
+            Ok(price) => match oracle.amount_at(price, NATIVE_DECIMALS) {
+                Some(amount) => (amount, Some(price)),
+                None => {
+                    warn!(
+                        "--usd-amount is worth less than one base unit at price {}, using fixed amount",
+                        price
+                    );
+                    (state.tokens_per_request, None)
+                }
+            },
+            Err(e) => {
+                warn!("price oracle unavailable, using fixed amount: {}", e);
+                (state.tokens_per_request, None)
+            }
+        },
+        (None, None) => (state.tokens_per_request, None),
+    };
+
+    // Recipients that are contracts may need more than the configured gas limit
+    let gas_limit = if state.estimate_gas {
+        let tx = TransactionRequest::default()
+            .from(state.wallet.default_signer().address())
+            .to(to_address)
+            .value(base_amount)
+            .input(calldata.clone().into());
+        match state.provider.estimate_gas(&tx).await {
+            Ok(estimate) => gas_limit.max(U256::from(estimate)),
+            Err(e) => {
+                warn!("gas estimation failed, using configured gas limit: {}", e);
+                gas_limit
+            }
         }
+    } else {
+        gas_limit
     };
 
+    let extra_fee = gas_price.saturating_sub(state.gas_price) * gas_limit;
+    let amount = base_amount.saturating_sub(extra_fee);
+    if amount.is_zero() {
+        return Err(FaucetError::new(
+            StatusCode::BAD_REQUEST,
+            "GAS_BID_TOO_HIGH",
+            "Gas price bid exceeds the amount per request",
+        ));
+    }
+
+    let fees = with_fee_floor(state, gas_price).await;
+    let gas_price = fees.max_fee;
+
     // Get the wallet address from state
     let from_address = state.wallet.default_signer().address();
 
     // Balance validations
-    let sender_balance = match state.provider.get_balance(from_address).await {
+    let sender_balance = match state.balance.get(&state.provider, from_address).await {
         Ok(b) => b,
         Err(e) => {
-            return HttpResponse::InternalServerError().json(ErrorResponse {
-                error: format!("Failed to get balance: {}", e),
-            })
+            return Err(FaucetError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "RPC_ERROR",
+                format!("Failed to get balance: {}", e),
+            ))
         }
     };
-    if sender_balance < state.tokens_per_request {
-        return HttpResponse::BadRequest().json(ErrorResponse {
-            error: "Insufficient balance".to_string(),
-        });
-    }
+    // Keep enough to pay for gas, optionally sending whatever is left when running low
+    let affordable = sender_balance.saturating_sub(gas_limit * gas_price);
+    let (amount, reduced) = if affordable >= amount {
+        (amount, false)
+    } else if state.allow_partial_drip
+        && !affordable.is_zero()
+        && affordable >= state.min_fallback_amount
+    {
+        (affordable, true)
+    } else {
+        return Err(insufficient_balance(
+            state,
+            amount,
+            to_address,
+            &format!(
+                "balance is {}",
+                format_units(sender_balance, NATIVE_DECIMALS)
+            ),
+        ));
+    };
 
     let receiver_balance = match state.provider.get_balance(to_address).await {
         Ok(b) => b,
         Err(e) => {
-            return HttpResponse::InternalServerError().json(ErrorResponse {
-                error: format!("Failed to get balance: {}", e),
-            })
+            return Err(FaucetError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "RPC_ERROR",
+                format!("Failed to get balance: {}", e),
+            ))
         }
     };
     if receiver_balance > U256::ZERO {
-        return HttpResponse::BadRequest().json(ErrorResponse {
-            error: "Receiver already has a balance greater than 0".to_string(),
-        });
+        return Err(FaucetError::new(
+            StatusCode::BAD_REQUEST,
+            "RECIPIENT_HAS_BALANCE",
+            "Receiver already has a balance greater than 0",
+        ));
     }
 
-    // Get the next nonce for the wallet
-    let nonce = match state.provider.get_transaction_count(from_address).await {
-        Ok(n) => n,
+    // Get the next nonce for the wallet, reconciling the node's view with nonces handed out locally
+    let nonce = match state
+        .provider
+        .get_transaction_count(from_address)
+        .pending()
+        .await
+    {
+        Ok(n) => state.nonces.reserve(n),
         Err(e) => {
-            return HttpResponse::InternalServerError().json(ErrorResponse {
-                error: format!("Failed to get nonce: {}", e),
-            })
+            return Err(FaucetError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "RPC_ERROR",
+                format!("Failed to get nonce: {}", e),
+            ))
         }
     };
 
@@ -131,57 +1858,336 @@ async fn send_tokens(data: web::Json<FaucetRequest>, state: web::Data<AppState>)
     let chain_id = match state.provider.get_chain_id().await {
         Ok(id) => id,
         Err(e) => {
-            return HttpResponse::InternalServerError().json(ErrorResponse {
-                error: format!("Failed to get chain ID: {}", e),
-            })
+            return Err(FaucetError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "RPC_ERROR",
+                format!("Failed to get chain ID: {}", e),
+            ))
         }
     };
 
     // Build the transaction request
     let mut tx = TransactionRequest::default()
         .to(to_address)
-        .nonce(nonce)
-        .value(state.tokens_per_request)
-        .gas_limit(state.gas_limit.to::<u64>())
-        .max_fee_per_gas(state.gas_price.to::<u128>())
-        .max_priority_fee_per_gas(state.gas_price.to::<u128>());
+        .nonce(nonce.nonce)
+        .value(amount)
+        .gas_limit(gas_limit.to::<u64>());
 
     tx.set_chain_id(chain_id);
+    if !calldata.is_empty() {
+        tx.set_input(calldata.clone());
+    }
 
-    // Build and sign the transaction
-    let tx_envelope = match tx.build(&state.wallet).await {
-        Ok(envelope) => envelope,
-        Err(e) => {
-            return HttpResponse::InternalServerError().json(ErrorResponse {
-                error: format!("Failed to build transaction: {}", e),
-            })
+    // Hard cap on the outbound transaction rate, the slot is handed back unless the drip is sent
+    let interval_pass = match state.interval_gate.try_pass() {
+        Ok(pass) => pass,
+        Err(wait) => {
+            return Err(FaucetError::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                "FAUCET_BUSY",
+                format!("Faucet is busy, retry in {}ms", wait.as_millis().max(1)),
+            ))
         }
     };
 
-    // Send the transaction
-    match state.provider.send_tx_envelope(tx_envelope).await {
-        Ok(receipt) => {
+    // The claim is recorded before sending, under a placeholder hash until the transaction has one,
+    // so the cooldown check and the record are a single step for the store
+    let pseudonymous = data.pseudonymous(state.no_store_pii);
+    let sending = format!("sending:{:?}:{}", from_address, nonce.nonce);
+    let mut claim = Claim {
+        address: to_address,
+        ip: client_ip,
+        amount,
+        tx_hash: sending.clone(),
+        claimed_at: chrono::Utc::now().timestamp(),
+        faucet_id: state.faucet_id.clone(),
+    };
+    if pseudonymous {
+        claim.address = state.pseudonymizer.address(to_address);
+        claim.ip = client_ip.map(|ip| state.pseudonymizer.ip(ip));
+    }
+    if cooldown_secs > 0 {
+        let since = claim.claimed_at - cooldown_secs as i64;
+        match state
+            .store
+            .record_claim_unless_claimed_since(&claim, &claim_keys(state, to_address), since)
+            .await
+        {
+            Ok(true) => {}
+            Ok(false) => {
+                return Err(FaucetError::new(
+                    StatusCode::TOO_MANY_REQUESTS,
+                    "COOLDOWN",
+                    "Receiver is on cooldown",
+                ))
+            }
+            Err(e) => {
+                return Err(FaucetError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "STORE_ERROR",
+                    format!("Failed to check cooldown: {}", e),
+                ))
+            }
+        }
+    } else if let Err(e) = state.store.record_claim(&claim).await {
+        warn!("failed to record claim for {:?}: {}", to_address, e);
+    }
+
+    let result = send_transfer(state, tx, fees, nonce.nonce).await;
+    if result.is_err() {
+        if let Err(e) = state.store.remove_claim(&sending).await {
+            warn!("failed to remove claim for {:?}: {}", to_address, e);
+        }
+    }
+    match result {
+        Ok(Sent { tx_hash, private }) => {
+            let formatted_amount = format_units(amount, NATIVE_DECIMALS);
             info!(
-                "sent tokens: {:?} to {:?}. Tx hash: {:?}",
-                state.tokens_per_request,
-                to_address,
-                receipt.tx_hash()
+                "sent tokens: {} to {:?}. Tx hash: {:?}",
+                formatted_amount, to_address, tx_hash
             );
-            HttpResponse::Ok().json(FaucetResponse {
-                transaction_hash: format!("{:?}", receipt.tx_hash()),
+
+            claim.tx_hash = if pseudonymous {
+                state.pseudonymizer.tx_hash(tx_hash)
+            } else {
+                format!("{:?}", tx_hash)
+            };
+            if let Err(e) = state
+                .store
+                .set_claim_tx_hash(&sending, &claim.tx_hash)
+                .await
+            {
+                warn!("failed to record claim for {:?}: {}", to_address, e);
+            }
+
+            pending.submitted(tx_hash);
+            interval_pass.commit();
+            let nonce = nonce.commit();
+            state.low_balance.store(false, Ordering::Relaxed);
+            state.balance.debit(amount + gas_limit * gas_price);
+            Ok(Drip {
+                to: to_address,
+                tx_hash,
+                nonce,
+                private,
+                amount,
+                reduced,
+                usd_rate,
+                gas_limit,
+                fees,
+                calldata,
+                claim,
+                pseudonymous,
             })
         }
-        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
-            error: format!("Failed to send transaction: {}", e),
-        }),
+        Err(SendError::Timeout(tx_hash)) => Err(FaucetError::new(
+            StatusCode::GATEWAY_TIMEOUT,
+            "SEND_TIMEOUT",
+            format!("Timed out broadcasting transaction {:?}", tx_hash),
+        )),
+        Err(SendError::NotAccepted(tx_hash)) => Err(FaucetError::new(
+            StatusCode::BAD_GATEWAY,
+            "BROADCAST_NOT_ACCEPTED",
+            format!(
+                "The node returned a hash but did not accept transaction {:?}",
+                tx_hash
+            ),
+        )),
+        Err(SendError::Build(e)) => Err(FaucetError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "TRANSACTION_BUILD_FAILED",
+            format!("Failed to build transaction: {}", e),
+        )),
+        // The balance check passed but the fee moved before the node enforced it
+        Err(SendError::Rpc(e)) if is_insufficient_funds(&e) => {
+            state.balance.invalidate();
+            Err(insufficient_balance(
+                state,
+                amount,
+                to_address,
+                &e.to_string(),
+            ))
+        }
+        Err(SendError::Rpc(e)) => Err(FaucetError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "TRANSACTION_SEND_FAILED",
+            format!("Failed to send transaction: {}", e),
+        )),
+        Err(SendError::Relay(e)) => Err(FaucetError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "TRANSACTION_SEND_FAILED",
+            format!("Failed to send transaction to the private relay: {}", e),
+        )),
+    }
+}
+
+/// Static details about this faucet instance.
+async fn faucet_info(req: HttpRequest, state: web::Data<AppState>) -> HttpResponse {
+    let version = match ResponseVersion::from_request(&req, state.response_version) {
+        Ok(version) => version,
+        Err(e) => return e.render(state.response_version),
+    };
+
+    let info = serde_json::json!({
+        "faucet_id": state.faucet_id,
+        "address": state.wallet.default_signer().address().to_string(),
+        "amount_per_request": format_units(state.tokens_per_request, NATIVE_DECIMALS),
+        "cooldown_secs": state.cooldown_secs,
+        "presets": state
+            .amount_presets
+            .iter()
+            .map(|(name, amount)| serde_json::json!({
+                "name": name,
+                "amount": format_units(*amount, NATIVE_DECIMALS),
+            }))
+            .collect::<Vec<_>>(),
+    });
+    signed_json(
+        &state,
+        HttpResponse::Ok(),
+        &response::envelope(version, &info),
+    )
+}
+
+/// Gas price for a request's bid, clamped to the bid range, or the configured one without a bid.
+fn bid_gas_price(state: &AppState, bid_gwei: Option<u64>) -> U256 {
+    match bid_gwei {
+        Some(gwei) => {
+            let gwei = gwei.clamp(state.min_gas_price_gwei, state.max_gas_price_gwei);
+            U256::from(gwei) * U256::from(WEI_PER_GWEI)
+        }
+        None => state.gas_price,
+    }
+}
+
+/// Number of drips of `amount` plus `gas_cost` that `balance` can fund, `None` when drips cost nothing.
+fn drip_runway(balance: U256, amount: U256, gas_cost: U256) -> Option<U256> {
+    balance.checked_div(amount.saturating_add(gas_cost))
+}
+
+/// Gas limit and most a drip without a gas bid pays per gas.
+async fn default_drip_fees(state: &AppState) -> (U256, U256) {
+    let gas_limit = state.gas_limit + U256::from(calldata_gas(&drip_calldata(state)));
+    (
+        gas_limit,
+        with_fee_floor(state, state.gas_price).await.max_fee,
+    )
+}
+
+/// Gas cost of a default drip at the current fees and how many the cached balance can still fund.
+async fn cost_estimate(req: HttpRequest, state: web::Data<AppState>) -> HttpResponse {
+    if !state.enable_cost_estimate {
+        return HttpResponse::NotFound().finish();
     }
+    let version = match ResponseVersion::from_request(&req, state.response_version) {
+        Ok(version) => version,
+        Err(e) => return e.render(state.response_version),
+    };
+
+    let from_address = state.wallet.default_signer().address();
+    let balance = match state.balance.get(&state.provider, from_address).await {
+        Ok(b) => b,
+        Err(e) => {
+            return FaucetError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "RPC_ERROR",
+                format!("Failed to get balance: {}", e),
+            )
+            .render(version)
+        }
+    };
+    let (gas_limit, gas_price) = default_drip_fees(&state).await;
+    let gas_cost = gas_limit * gas_price;
+    // Unbounded, reported as null, when drips cost nothing
+    let drips_remaining = drip_runway(balance, state.tokens_per_request, gas_cost)
+        .map(|runway| runway.saturating_to::<u64>());
+
+    let estimate = serde_json::json!({
+        "gas_limit": gas_limit.to::<u64>(),
+        "gas_price_wei": gas_price.to_string(),
+        "gas_price_gwei": format_units(gas_price, 9),
+        "gas_cost_per_drip_wei": gas_cost.to_string(),
+        "gas_cost_per_drip": format_units(gas_cost, NATIVE_DECIMALS),
+        "amount_per_request": format_units(state.tokens_per_request, NATIVE_DECIMALS),
+        "balance_wei": balance.to_string(),
+        "balance": format_units(balance, NATIVE_DECIMALS),
+        "drips_remaining": drips_remaining,
+    });
+    HttpResponse::Ok().json(response::envelope(version, &estimate))
+}
+
+/// Reports the faucet as degraded while its balance can't cover a drip, or when block freshness
+/// checking is enabled and the chain has stalled.
+async fn health_check(state: web::Data<AppState>) -> HttpResponse {
+    let balance_cache_age_ms = state.balance.age().map(|age| age.as_millis() as u64);
+    let low_balance = state.low_balance.load(Ordering::Relaxed);
+    let Some(max_age) = state.max_block_age_secs else {
+        let body = serde_json::json!({
+            "status": if low_balance { "degraded" } else { "healthy" },
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "balance_cache_age_ms": balance_cache_age_ms,
+            "low_balance": low_balance,
+            "pending_drips": state.pending.len(),
+            "max_pending_drips": state.pending.max_pending(),
+            "mempool_pending": state.mempool.last_observed(),
+        });
+        let response = if low_balance {
+            HttpResponse::ServiceUnavailable()
+        } else {
+            HttpResponse::Ok()
+        };
+        return signed_json(&state, response, &body);
+    };
+
+    let block_age = latest_block_age(&state.provider).await.ok();
+    let healthy = !low_balance && block_age.is_some_and(|age| age <= max_age);
+    let body = serde_json::json!({
+        "status": if healthy { "healthy" } else { "degraded" },
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "latest_block_age_secs": block_age,
+        "balance_cache_age_ms": balance_cache_age_ms,
+        "low_balance": low_balance,
+        "pending_drips": state.pending.len(),
+        "max_pending_drips": state.pending.max_pending(),
+        "mempool_pending": state.mempool.last_observed(),
+    });
+
+    let response = if healthy {
+        HttpResponse::Ok()
+    } else {
+        HttpResponse::ServiceUnavailable()
+    };
+    signed_json(&state, response, &body)
 }
 
-async fn health_check() -> HttpResponse {
-    HttpResponse::Ok().json(serde_json::json!({
-        "status": "healthy",
-        "timestamp": chrono::Utc::now().to_rfc3339()
-    }))
+/// Prints the most recent drips to `address`, whether their claims were stored raw or hashed.
+async fn show_history(args: &Args, pseudonymizer: &Pseudonymizer, address: Address) -> Result<()> {
+    let store = store::open(args.store_backend, args.store_url.as_deref())
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    let mut claims = Vec::new();
+    for key in [address, pseudonymizer.address(address)] {
+        claims.extend(
+            store
+                .history(key, HISTORY_LIMIT)
+                .await
+                .map_err(|e| std::io::Error::other(e.to_string()))?,
+        );
+    }
+    claims.sort_by_key(|claim| std::cmp::Reverse(claim.claimed_at));
+    claims.truncate(HISTORY_LIMIT as usize);
+
+    for claim in claims {
+        println!(
+            "{} {} {}",
+            chrono::DateTime::from_timestamp(claim.claimed_at, 0)
+                .unwrap_or_default()
+                .to_rfc3339(),
+            format_units(claim.amount, NATIVE_DECIMALS),
+            claim.tx_hash
+        );
+    }
+    Ok(())
 }
 
 #[actix_web::main]
@@ -207,40 +2213,334 @@ async fn main() -> Result<()> {
     let fixed_bytes = alloy_primitives::FixedBytes::from(private_key_bytes);
     let signer = PrivateKeySigner::from_bytes(&fixed_bytes)
         .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
+    let response_signer = args.sign_responses.then(|| signer.clone());
+    let pseudonymizer = match &args.pii_salt {
+        Some(salt) => Pseudonymizer::new(salt.as_bytes()),
+        None => Pseudonymizer::new(&private_key_bytes),
+    };
+    if let Some(address) = args.show_history {
+        return show_history(&args, &pseudonymizer, address).await;
+    }
+    // Relay requests are signed with the faucet key
+    let private_relay = args
+        .private_relay_url
+        .clone()
+        .map(|url| PrivateRelay::new(url, signer.clone()));
     let wallet = EthereumWallet::from(signer);
 
     // Setup provider with wallet
-    let url = args.rpc_url.parse().expect("should parse rpc url");
-    let provider = ProviderBuilder::new().on_http(url);
+    let trace_layer = RpcTraceLayer::new(args.trace_rpc, args.max_logged_body_bytes);
+    let connect = |url: &str| {
+        let url = url.parse().map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Invalid RPC URL {}: {}", url, e),
+            )
+        })?;
+        let client = ClientBuilder::default().layer(trace_layer).http(url);
+        Ok::<_, std::io::Error>(ProviderBuilder::new().on_client(client))
+    };
+    let provider = connect(&args.rpc_url)?;
+
+    // Fail fast on an unreachable RPC, or wait for it when started alongside it
+    let chain_id = if args.wait_for_rpc {
+        let timeout = Duration::from_secs(args.rpc_wait_timeout_secs);
+        wait_for_rpc(&provider, &args.rpc_url, timeout).await?
+    } else {
+        provider.get_chain_id().await.map_err(|e| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotConnected,
+                format!("cannot reach RPC at {}: {}", args.rpc_url, e),
+            )
+        })?
+    };
+    info!(
+        "connected to RPC at {} (chain id {})",
+        args.rpc_url, chain_id
+    );
 
     // Parse tokens per request
     let tokens_per_request = U256::from_str_radix(&args.tokens_per_request, 10)
         .expect("Invalid tokens_per_request value");
+    let min_fallback_amount = U256::from_str_radix(&args.min_fallback_amount, 10)
+        .expect("Invalid min_fallback_amount value");
+
+    let amount_presets = args
+        .amount_presets
+        .iter()
+        .map(|preset| parse_amount_preset(preset))
+        .collect::<Result<Vec<_>>>()?;
 
     // Convert gas price from gwei to wei
-    let gas_price = U256::from(args.gas_price_gwei) * U256::from(1_000_000_000);
+    let gas_price = U256::from(args.gas_price_gwei) * U256::from(WEI_PER_GWEI);
     let gas_limit = U256::from(args.gas_limit);
+    // Bids below the configured price would underpay the faucet's own default
+    let min_gas_price_gwei = args.min_gas_price_gwei.unwrap_or(args.gas_price_gwei);
+    if min_gas_price_gwei < args.gas_price_gwei {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--min-gas-price-gwei must not be below --gas-price-gwei",
+        ));
+    }
+    if min_gas_price_gwei > args.max_gas_price_gwei {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--gas-price-gwei and --min-gas-price-gwei must not exceed --max-gas-price-gwei",
+        ));
+    }
+    if let Some(usd_amount) = args.usd_amount {
+        if !usd_amount.is_finite() || usd_amount <= 0.0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "--usd-amount must be a positive number",
+            ));
+        }
+    }
+    if args.max_usd_amount_multiple == 0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--max-usd-amount-multiple must be at least 1",
+        ));
+    }
+    if let Some(percentile) = args.priority_fee_percentile {
+        if !(0.0..=100.0).contains(&percentile) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "--priority-fee-percentile must be between 0 and 100",
+            ));
+        }
+    }
+
+    // Setup the optional RPC passthrough
+    let rpc_proxy = if args.enable_rpc_proxy {
+        let proxy = RpcProxy::new(&args.rpc_proxy_methods, args.rpc_proxy_requests_per_minute)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        Some(proxy)
+    } else {
+        None
+    };
+
+    // Connect to the extra broadcast endpoints
+    let broadcast_targets = if args.broadcast_to_all {
+        args.broadcast_rpc_urls
+            .iter()
+            .map(|url| Ok((url.clone(), connect(url)?)))
+            .collect::<Result<Vec<_>>>()?
+    } else {
+        Vec::new()
+    };
+
+    // Normalize allowed recipient prefixes to lowercase hex without 0x
+    let recipient_prefixes = args
+        .recipient_prefixes
+        .iter()
+        .map(|prefix| {
+            let prefix = prefix.strip_prefix("0x").unwrap_or(prefix).to_lowercase();
+            if prefix.len() > 40 || !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("Invalid recipient prefix {}", prefix),
+                ));
+            }
+            Ok(prefix)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // Normalize allowed origins to the form browsers send in the Origin header
+    let allowed_origins = args
+        .allowed_origins
+        .iter()
+        .map(|origin| {
+            url_origin(origin).ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("Invalid allowed origin {}", origin),
+                )
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // Validate custom response headers
+    let response_headers = response_headers(&args.response_headers)?;
+
+    // Load addresses that must never be funded
+    let known_addresses = match &args.known_addresses_file {
+        Some(path) => load_known_addresses(path)?,
+        None => HashMap::new(),
+    };
+
+    let trusted_proxies = IpRanges::parse(args.trusted_proxies.iter().map(String::as_str))
+        .map_err(|proxy| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Invalid trusted proxy {}", proxy),
+            )
+        })?;
+    let client_ip = ClientIpResolver::new(trusted_proxies, args.max_forwarded_hops);
+
+    let anonymizer_ranges = match &args.anonymizer_ranges_file {
+        Some(path) => IpRanges::load(path)?,
+        None => IpRanges::default(),
+    };
+
+    let Some(history_retention_secs) = retention_secs(args.history_retention_days) else {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--history-retention-days is out of range",
+        ));
+    };
+
+    // Cooldowns are checked against the stored claims, so they must outlive it
+    if args.history_retention_days > 0 {
+        let retention_secs = history_retention_secs.unsigned_abs();
+        if retention_secs < args.cooldown_secs {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "--history-retention-days must cover --cooldown-secs",
+            ));
+        }
+        if args.anonymizer_ranges_file.is_some()
+            && args.anonymizer_policy == AnonymizerPolicy::TighterLimit
+            && retention_secs < args.anonymizer_cooldown_secs
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "--history-retention-days must cover --anonymizer-cooldown-secs",
+            ));
+        }
+    }
+
+    let rejection_log = match &args.rejection_log_file {
+        Some(path) => RejectionLog::open(path)?,
+        None => RejectionLog::default(),
+    };
+
+    // Open the claim store
+    let store = store::open(args.store_backend, args.store_url.as_deref())
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    if args.reset_on_chain_change && !args.validate_only {
+        reset_on_chain_change(&provider, store.as_ref()).await?;
+    }
 
     // Create app state
     let state = web::Data::new(AppState {
         provider: Arc::new(provider),
         wallet,
+        response_signer,
         tokens_per_request,
+        amount_presets,
+        balance: BalanceCache::new(Duration::from_millis(args.balance_cache_max_age_ms)),
         gas_price,
         gas_limit,
+        estimate_gas: args.estimate_gas,
+        max_gas_bumps: args.max_gas_bumps,
+        min_gas_price_gwei,
+        max_gas_price_gwei: args.max_gas_price_gwei,
+        priority_fee_floor: args.priority_fee_percentile.map(PriorityFeeFloor::new),
+        max_block_age_secs: args.max_block_age_secs,
+        max_mempool_pending: args.max_mempool_pending,
+        mempool: MempoolMonitor::default(),
+        send_timeout: Duration::from_secs(args.send_timeout_secs),
+        confirmation_timeout: Duration::from_secs(args.confirmation_timeout_secs),
+        verify_broadcast: args.verify_broadcast,
+        rpc_proxy,
+        enable_cost_estimate: args.enable_cost_estimate,
+        known_addresses,
+        no_store_pii: args.no_store_pii,
+        pseudonymizer,
+        client_ip,
+        anonymizer_ranges,
+        anonymizer_policy: args.anonymizer_policy,
+        anonymizer_cooldown_secs: args.anonymizer_cooldown_secs,
+        min_response_time: args
+            .constant_time_response
+            .then(|| Duration::from_millis(args.min_response_ms)),
+        store,
+        cooldown_secs: args.cooldown_secs,
+        max_addresses_per_ip: args.max_addresses_per_ip,
+        eligibility: args.eligibility_service_url.map(|url| {
+            EligibilityService::new(url, Duration::from_millis(args.eligibility_timeout_ms))
+        }),
+        eligibility_fail_open: args.eligibility_fail_open,
+        faucet_id: args.faucet_id,
+        tag_calldata: args.tag_calldata,
+        allow_partial_drip: args.allow_partial_drip,
+        min_fallback_amount,
+        recipient_prefixes,
+        legacy_transactions: AtomicBool::new(false),
+        low_balance: AtomicBool::new(false),
+        in_flight: InFlightLimiter::new(args.max_concurrent_per_ip),
+        pending: PendingDrips::new(args.max_pending_drips),
+        explorer: args
+            .explorer_api_url
+            .map(|url| Explorer::new(url, args.explorer_api_key)),
+        nonces: NonceTracker::new(args.resync_nonce_down),
+        rejection_log,
+        interval_gate: IntervalGate::new(Duration::from_millis(args.global_min_interval_ms)),
+        broadcast_targets,
+        private_relay,
+        relay_fallback: args.relay_fallback,
+        response_version: args.response_version,
+        allowed_origins,
+        require_origin: args.require_origin,
+        price_oracle: args.price_oracle_url.map(|url| {
+            PriceOracle::new(
+                url,
+                args.price_oracle_path,
+                args.usd_amount.unwrap_or_default(),
+                tokens_per_request.saturating_mul(U256::from(args.max_usd_amount_multiple)),
+            )
+        }),
     });
 
+    // Pre-flight report, so misconfigurations show up before the first request
+    let fatal = preflight::run(&state, &args.rpc_url, chain_id).await;
+    if fatal > 0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("pre-flight check found {} fatal issue(s)", fatal),
+        ));
+    }
+    if args.validate_only {
+        return Ok(());
+    }
+
+    if args.history_retention_days > 0 {
+        actix_web::rt::spawn(prune_claims(
+            state.clone(),
+            args.history_retention_days,
+            history_retention_secs,
+        ));
+    }
+
     // Start server
     HttpServer::new(move || {
         // Setup CORS
-        let cors = Cors::permissive();
+        let cors = state
+            .allowed_origins
+            .iter()
+            .fold(Cors::permissive(), |cors, origin| {
+                cors.allowed_origin(origin)
+            });
+
+        let headers = response_headers
+            .iter()
+            .cloned()
+            .fold(middleware::DefaultHeaders::new(), |headers, header| {
+                headers.add(header)
+            });
 
         App::new()
+            .wrap(headers)
             .wrap(cors)
             .wrap(middleware::Logger::default())
             .app_data(state.clone())
-            .route("/faucet", web::post().to(send_tokens))
+            .service(faucet_resource())
             .route("/health", web::get().to(health_check))
+            .route("/info", web::get().to(faucet_info))
+            .route("/rpc", web::post().to(proxy_rpc))
+            .route("/cost-estimate", web::get().to(cost_estimate))
     })
     .bind((args.host, args.port))?
     .run()
@@ -248,3 +2548,726 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloy_provider::network::{eip2718::Decodable2718, Ethereum, Network};
+    use serde_json::{json, Value};
+    use std::sync::Mutex;
+
+    /// Anvil's first development key.
+    const TEST_KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+    pub(crate) type RpcHandler =
+        Arc<dyn Fn(&str, &Value) -> std::result::Result<Value, String> + Send + Sync>;
+
+    /// Serves JSON-RPC on a local port, answering each call with `handler(method, params)`.
+    pub(crate) fn mock_rpc(handler: RpcHandler) -> String {
+        let server = HttpServer::new(move || {
+            let handler = handler.clone();
+            App::new().route(
+                "/",
+                web::post().to(move |body: web::Json<Value>| {
+                    let handler = handler.clone();
+                    async move {
+                        let method = body["method"].as_str().unwrap_or_default();
+                        let response = match handler(method, &body["params"]) {
+                            Ok(result) => {
+                                json!({ "jsonrpc": "2.0", "id": body["id"], "result": result })
+                            }
+                            Err(message) => json!({
+                                "jsonrpc": "2.0",
+                                "id": body["id"],
+                                "error": { "code": -32000, "message": message },
+                            }),
+                        };
+                        HttpResponse::Ok().json(response)
+                    }
+                }),
+            )
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let url = format!("http://{}", server.addrs()[0]);
+        actix_web::rt::spawn(server.run());
+        url
+    }
+
+    /// Records the raw transactions sent to a mock node.
+    #[derive(Clone, Default)]
+    struct SentRaw(Arc<Mutex<Vec<String>>>);
+
+    impl SentRaw {
+        /// Accepts `raw`, answering with its hash like a node would.
+        fn accept(&self, raw: &Value) -> std::result::Result<Value, String> {
+            let raw = raw[0].as_str().unwrap_or_default().to_string();
+            let hash = alloy_primitives::keccak256(hex::decode(&raw[2..]).unwrap());
+            self.0.lock().unwrap().push(raw);
+            Ok(json!(hash))
+        }
+
+        fn list(&self) -> Vec<String> {
+            self.0.lock().unwrap().clone()
+        }
+    }
+
+    /// Faucet state with default settings, talking to the node at `rpc_url`.
+    pub(crate) fn test_state(rpc_url: &str) -> AppState {
+        let signer: PrivateKeySigner = TEST_KEY.parse().unwrap();
+        let client = ClientBuilder::default()
+            .layer(RpcTraceLayer::new(false, 0))
+            .http(rpc_url.parse().unwrap());
+        AppState {
+            provider: Arc::new(ProviderBuilder::new().on_client(client)),
+            wallet: EthereumWallet::from(signer),
+            response_signer: None,
+            tokens_per_request: U256::from(1_000_000_000_000_000_000u128),
+            amount_presets: Vec::new(),
+            balance: BalanceCache::new(Duration::ZERO),
+            gas_price: U256::from(WEI_PER_GWEI),
+            gas_limit: U256::from(21_000),
+            estimate_gas: false,
+            max_gas_bumps: 0,
+            min_gas_price_gwei: 1,
+            max_gas_price_gwei: 100,
+            priority_fee_floor: None,
+            max_block_age_secs: None,
+            max_mempool_pending: None,
+            mempool: MempoolMonitor::default(),
+            send_timeout: Duration::from_secs(10),
+            confirmation_timeout: Duration::from_secs(600),
+            verify_broadcast: false,
+            rpc_proxy: None,
+            enable_cost_estimate: false,
+            known_addresses: HashMap::new(),
+            client_ip: ClientIpResolver::new(IpRanges::default(), 10),
+            anonymizer_ranges: IpRanges::default(),
+            anonymizer_policy: AnonymizerPolicy::TighterLimit,
+            anonymizer_cooldown_secs: 0,
+            min_response_time: None,
+            store: Box::new(store::MemoryClaimStore::default()),
+            cooldown_secs: 0,
+            max_addresses_per_ip: None,
+            eligibility: None,
+            eligibility_fail_open: false,
+            faucet_id: None,
+            tag_calldata: false,
+            allow_partial_drip: false,
+            min_fallback_amount: U256::ZERO,
+            recipient_prefixes: Vec::new(),
+            price_oracle: None,
+            legacy_transactions: AtomicBool::new(false),
+            low_balance: AtomicBool::new(false),
+            in_flight: InFlightLimiter::new(0),
+            pending: PendingDrips::new(1000),
+            explorer: None,
+            nonces: NonceTracker::default(),
+            rejection_log: RejectionLog::default(),
+            no_store_pii: false,
+            pseudonymizer: Pseudonymizer::new(b"test"),
+            interval_gate: IntervalGate::new(Duration::ZERO),
+            broadcast_targets: Vec::new(),
+            private_relay: None,
+            relay_fallback: false,
+            response_version: ResponseVersion::V1,
+            allowed_origins: Vec::new(),
+            require_origin: false,
+        }
+    }
+
+    /// A signable transfer with every field but the fees set.
+    fn transfer(nonce: u64) -> TransactionRequest {
+        let mut tx = TransactionRequest::default()
+            .to(Address::with_last_byte(1))
+            .nonce(nonce)
+            .value(U256::from(1))
+            .gas_limit(21_000);
+        tx.set_chain_id(1);
+        tx
+    }
+
+    fn node_error(message: &str) -> TransportError {
+        TransportError::ErrorResp(alloy_json_rpc::ErrorPayload {
+            code: -32000,
+            message: message.to_string().into(),
+            data: None,
+        })
+    }
+
+    fn request(body: serde_json::Value) -> FaucetRequest {
+        serde_json::from_value(body).unwrap()
+    }
+
+    /// A node where the faucet holds `faucet_balance` wei and recipients hold nothing, sending
+    /// raw transactions to `send`.
+    fn funded_node(
+        faucet_balance: u128,
+        send: impl Fn(&Value) -> std::result::Result<Value, String> + Send + Sync + 'static,
+    ) -> String {
+        let faucet = format!(
+            "{:?}",
+            TEST_KEY.parse::<PrivateKeySigner>().unwrap().address()
+        );
+        mock_rpc(Arc::new(move |method, params| match method {
+            "eth_getBalance" if params[0].as_str() == Some(faucet.as_str()) => {
+                Ok(json!(U256::from(faucet_balance)))
+            }
+            "eth_getBalance" => Ok(json!("0x0")),
+            "eth_getTransactionCount" => Ok(json!("0x0")),
+            "eth_chainId" => Ok(json!("0x1")),
+            "eth_sendRawTransaction" => send(params),
+            _ => Err(format!("unexpected {}", method)),
+        }))
+    }
+
+    fn drip_request() -> FaucetRequest {
+        request(json!({ "address": Address::with_last_byte(2).to_string() }))
+    }
+
+    #[test]
+    fn recognizes_unsupported_tx_type_errors() {
+        for message in [
+            "transaction type not supported",
+            "Transaction type not supported",
+            "tx type not supported",
+            "invalid transaction: unsupported transaction type",
+        ] {
+            assert!(is_unsupported_tx_type(&node_error(message)), "{}", message);
+        }
+        for message in [
+            "insufficient funds for gas * price + value",
+            "nonce too low",
+            "replacement transaction underpriced",
+        ] {
+            assert!(!is_unsupported_tx_type(&node_error(message)), "{}", message);
+        }
+    }
+
+    #[actix_web::test]
+    async fn downgrades_to_legacy_when_the_node_rejects_eip1559() {
+        let sent = SentRaw::default();
+        let node = sent.clone();
+        let url = mock_rpc(Arc::new(move |method, params| match method {
+            // EIP-1559 envelopes start with their type byte
+            "eth_sendRawTransaction" if params[0].as_str().unwrap().starts_with("0x02") => {
+                Err("transaction type not supported".to_string())
+            }
+            "eth_sendRawTransaction" => node.accept(params),
+            _ => Err(format!("unexpected {}", method)),
+        }));
+        let state = test_state(&url);
+
+        let result = send_transfer(&state, transfer(0), Fees::flat(state.gas_price), 0).await;
+        assert!(result.is_ok());
+        assert!(state.legacy_transactions.load(Ordering::Relaxed));
+        let sent_raw = sent.list();
+        assert_eq!(sent_raw.len(), 1);
+        assert!(!sent_raw[0].starts_with("0x02"));
+
+        // The downgrade sticks for later transactions
+        assert!(
+            send_transfer(&state, transfer(1), Fees::flat(state.gas_price), 1)
+                .await
+                .is_ok()
+        );
+        assert_eq!(sent.list().len(), 2);
+    }
+
+    #[test]
+    fn format_units_scales_by_decimals() {
+        assert_eq!(format_units(U256::from(1_500_000), 6), "1.5");
+        assert_eq!(format_units(U256::from(12_345_678), 8), "0.12345678");
+        assert_eq!(
+            format_units(U256::from(1_000_000_000_000_000_000u128), 18),
+            "1"
+        );
+        assert_eq!(format_units(U256::from(1), 18), "0.000000000000000001");
+    }
+
+    #[test]
+    fn format_units_trims_trailing_zeros() {
+        assert_eq!(
+            format_units(U256::from(2_500_000_000_000_000_000u128), 18),
+            "2.5"
+        );
+        assert_eq!(format_units(U256::from(10_000_000), 6), "10");
+        assert_eq!(format_units(U256::from(100_010_000), 8), "1.0001");
+    }
+
+    #[test]
+    fn format_units_zero() {
+        assert_eq!(format_units(U256::ZERO, 18), "0");
+        assert_eq!(format_units(U256::from(42), 0), "42");
+    }
+
+    #[test]
+    fn store_false_rejection_logs_no_pii() {
+        let path = std::env::temp_dir().join(format!("rejections-{}.jsonl", std::process::id()));
+        let log = RejectionLog::open(&path).unwrap();
+        let address = "0x0000000000000000000000000000000000000001";
+        let ip: IpAddr = "203.0.113.7".parse().unwrap();
+
+        let opted_out = request(serde_json::json!({ "address": address, "store": false }));
+        log_rejection(&log, "COOLDOWN", &opted_out, Some(ip), false);
+        let stored = request(serde_json::json!({ "address": address }));
+        log_rejection(&log, "COOLDOWN", &stored, Some(ip), true);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(contents.lines().count(), 2);
+        assert!(!contents.contains(address));
+        assert!(!contents.contains("203.0.113.7"));
+    }
+
+    #[test]
+    fn stored_rejection_logs_recipient_and_ip() {
+        let path =
+            std::env::temp_dir().join(format!("rejections-raw-{}.jsonl", std::process::id()));
+        let log = RejectionLog::open(&path).unwrap();
+        let address = "0x0000000000000000000000000000000000000001";
+
+        let data = request(serde_json::json!({ "address": address, "store": true }));
+        log_rejection(&log, "COOLDOWN", &data, "203.0.113.7".parse().ok(), false);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(contents.contains(address));
+        assert!(contents.contains("203.0.113.7"));
+    }
+
+    #[actix_web::test]
+    async fn low_balance_is_rejected_with_503() {
+        let state = test_state(&funded_node(1_000, |_| Err("unexpected send".to_string())));
+
+        let error = dispense(&drip_request(), None, &state).await.err().unwrap();
+        assert_eq!(error.status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(error.code, "INSUFFICIENT_FAUCET_BALANCE");
+        assert_eq!(error.retry_after, Some(INSUFFICIENT_FUNDS_RETRY_SECS));
+        assert!(state.low_balance.load(Ordering::Relaxed));
+    }
+
+    #[actix_web::test]
+    async fn node_insufficient_funds_is_rejected_with_503() {
+        // The balance check passes, but the node enforces a higher fee
+        let url = funded_node(10_000_000_000_000_000_000, |_| {
+            Err("insufficient funds for gas * price + value: balance 1, tx cost 2".to_string())
+        });
+        let state = test_state(&url);
+
+        let error = dispense(&drip_request(), None, &state).await.err().unwrap();
+        assert_eq!(error.status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(error.code, "INSUFFICIENT_FAUCET_BALANCE");
+        assert_eq!(error.retry_after, Some(INSUFFICIENT_FUNDS_RETRY_SECS));
+        assert!(state.low_balance.load(Ordering::Relaxed));
+        assert!(state.balance.age().is_none());
+    }
+
+    #[actix_web::test]
+    async fn successful_drip_clears_low_balance() {
+        let sent = SentRaw::default();
+        let node = sent.clone();
+        let state = test_state(&funded_node(10_000_000_000_000_000_000, move |params| {
+            node.accept(params)
+        }));
+        state.low_balance.store(true, Ordering::Relaxed);
+
+        assert!(dispense(&drip_request(), None, &state).await.is_ok());
+        assert_eq!(sent.list().len(), 1);
+        assert!(!state.low_balance.load(Ordering::Relaxed));
+    }
+
+    fn receipt(status: &str, gas_used: u64) -> Value {
+        json!({
+            "transactionHash": TxHash::with_last_byte(1),
+            "transactionIndex": "0x0",
+            "blockHash": TxHash::with_last_byte(2),
+            "blockNumber": "0x2a",
+            "from": Address::with_last_byte(3),
+            "to": Address::with_last_byte(4),
+            "cumulativeGasUsed": format!("{:#x}", gas_used),
+            "gasUsed": format!("{:#x}", gas_used),
+            "effectiveGasPrice": "0x3b9aca00",
+            "contractAddress": null,
+            "logs": [],
+            "logsBloom": Bytes::from(vec![0u8; 256]),
+            "type": "0x2",
+            "status": status,
+        })
+    }
+
+    #[test]
+    fn reverted_receipt_is_mined_but_failed() {
+        let reverted: TransactionReceipt = serde_json::from_value(receipt("0x0", 21_000)).unwrap();
+        assert!(matches!(
+            receipt_status(&reverted),
+            DripStatus::Mined {
+                success: false,
+                block: Some(42),
+                gas_used: Some(21_000),
+            }
+        ));
+
+        let mined: TransactionReceipt = serde_json::from_value(receipt("0x1", 21_000)).unwrap();
+        assert!(matches!(
+            receipt_status(&mined),
+            DripStatus::Mined { success: true, .. }
+        ));
+    }
+
+    #[actix_web::test]
+    async fn watcher_sees_reverted_drip_as_mined_and_failed() {
+        let url = mock_rpc(Arc::new(|method, _| match method {
+            "eth_getTransactionReceipt" => Ok(receipt("0x0", 30_000)),
+            _ => Err(format!("unexpected {}", method)),
+        }));
+        let state = test_state(&url);
+
+        let status = drip_status(&state, TxHash::with_last_byte(1), false).await;
+        assert!(matches!(
+            status,
+            DripStatus::Mined {
+                success: false,
+                block: Some(42),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn out_of_gas_drip_is_bumped_up_to_the_cap() {
+        let limit = U256::from(21_000);
+        assert_eq!(
+            bumped_gas_limit(Some(21_000), limit, 0, 2),
+            Some(U256::from(42_000))
+        );
+        assert_eq!(
+            bumped_gas_limit(Some(42_000), U256::from(42_000), 1, 2),
+            Some(U256::from(84_000))
+        );
+        assert_eq!(
+            bumped_gas_limit(Some(84_000), U256::from(84_000), 2, 2),
+            None
+        );
+        assert_eq!(bumped_gas_limit(Some(21_000), limit, 0, 0), None);
+    }
+
+    #[test]
+    fn other_reverts_are_not_bumped() {
+        let limit = U256::from(50_000);
+        assert_eq!(bumped_gas_limit(Some(30_000), limit, 0, 3), None);
+        assert_eq!(bumped_gas_limit(None, limit, 0, 3), None);
+    }
+
+    /// A drip of 1 wei to `with_last_byte(2)` sent with nonce 0 and a 21000 gas limit.
+    fn sent_drip(state: &AppState, tx_hash: TxHash) -> Drip {
+        let to = Address::with_last_byte(2);
+        Drip {
+            to,
+            tx_hash,
+            nonce: 0,
+            private: false,
+            amount: U256::from(1),
+            reduced: false,
+            usd_rate: None,
+            gas_limit: U256::from(21_000),
+            fees: Fees::flat(state.gas_price),
+            calldata: Bytes::new(),
+            claim: Claim {
+                address: to,
+                ip: None,
+                amount: U256::from(1),
+                tx_hash: format!("{:?}", tx_hash),
+                claimed_at: 0,
+                faucet_id: None,
+            },
+            pseudonymous: false,
+        }
+    }
+
+    #[actix_web::test]
+    async fn out_of_gas_drip_is_resent_with_the_bumped_limit() {
+        let sent = SentRaw::default();
+        let node = sent.clone();
+        let url = mock_rpc(Arc::new(move |method, params| match method {
+            // The reverted drip used nonce 0
+            "eth_getTransactionCount" => Ok(json!("0x1")),
+            "eth_chainId" => Ok(json!("0x1")),
+            "eth_sendRawTransaction" => node.accept(params),
+            _ => Err(format!("unexpected {}", method)),
+        }));
+        let mut state = test_state(&url);
+        state.max_gas_bumps = 1;
+
+        let drip = sent_drip(&state, TxHash::with_last_byte(1));
+        let gas_limit =
+            bumped_gas_limit(Some(21_000), drip.gas_limit, 0, state.max_gas_bumps).unwrap();
+        let (resent, nonce) = resend_drip(&state, &drip, gas_limit).await.ok().unwrap();
+        assert_eq!(nonce, 1);
+        let raw = sent.list();
+        assert_eq!(raw.len(), 1);
+        assert_eq!(
+            resent.tx_hash,
+            alloy_primitives::keccak256(hex::decode(&raw[0][2..]).unwrap())
+        );
+        let resent = hex::decode(&raw[0][2..]).unwrap();
+        type TxEnvelope = <Ethereum as Network>::TxEnvelope;
+        let TxEnvelope::Eip1559(resent) = TxEnvelope::decode_2718(&mut resent.as_slice()).unwrap()
+        else {
+            panic!("expected an EIP-1559 transaction");
+        };
+        assert_eq!((resent.tx().gas_limit, resent.tx().nonce), (42_000, 1));
+    }
+
+    /// A node that accepts every transaction but only knows it after `known_after` lookups.
+    fn forgetful_node(sent: &SentRaw, known_after: Option<u32>) -> String {
+        let node = sent.clone();
+        let lookups = Arc::new(Mutex::new(0));
+        mock_rpc(Arc::new(move |method, params| match method {
+            "eth_sendRawTransaction" => node.accept(params),
+            "eth_getTransactionByHash" => {
+                let mut lookups = lookups.lock().unwrap();
+                *lookups += 1;
+                match known_after {
+                    Some(known_after) if *lookups > known_after => Ok(json!({ "hash": params[0] })),
+                    _ => Ok(Value::Null),
+                }
+            }
+            _ => Err(format!("unexpected {}", method)),
+        }))
+    }
+
+    #[actix_web::test]
+    async fn hash_for_an_unaccepted_transaction_fails_verification() {
+        let sent = SentRaw::default();
+        let mut state = test_state(&forgetful_node(&sent, None));
+        state.verify_broadcast = true;
+
+        let result = send_transfer(&state, transfer(0), Fees::flat(state.gas_price), 0).await;
+        assert!(matches!(result, Err(SendError::NotAccepted(_))));
+        // Sent once and resent after every failed lookup but the last
+        assert_eq!(sent.list().len(), VERIFY_BROADCAST_ATTEMPTS as usize);
+    }
+
+    #[actix_web::test]
+    async fn transaction_known_after_a_resend_passes_verification() {
+        let sent = SentRaw::default();
+        let mut state = test_state(&forgetful_node(&sent, Some(1)));
+        state.verify_broadcast = true;
+
+        let result = send_transfer(&state, transfer(0), Fees::flat(state.gas_price), 0).await;
+        assert!(result.is_ok());
+        assert_eq!(sent.list().len(), 2);
+    }
+
+    #[test]
+    fn gas_bids_stay_within_the_bid_range() {
+        let mut state = test_state("http://127.0.0.1:1");
+        state.gas_price = U256::from(5 * WEI_PER_GWEI);
+        state.min_gas_price_gwei = 5;
+        let gwei = |gwei: u64| U256::from(gwei * WEI_PER_GWEI);
+
+        assert_eq!(bid_gas_price(&state, None), gwei(5));
+        assert_eq!(bid_gas_price(&state, Some(1)), gwei(5));
+        assert_eq!(bid_gas_price(&state, Some(20)), gwei(20));
+        assert_eq!(bid_gas_price(&state, Some(500)), gwei(100));
+    }
+
+    #[test]
+    fn fee_floor_raises_the_priority_fee_over_the_base_fee() {
+        let gwei = |gwei: u64| U256::from(gwei * WEI_PER_GWEI);
+        let recent = |base: u64, priority: u64| RecentFees {
+            base_fee: (base * WEI_PER_GWEI).into(),
+            priority_fee: (priority * WEI_PER_GWEI).into(),
+        };
+
+        let fees = floored_fees(gwei(1), recent(30, 2), gwei(100));
+        assert_eq!(fees.priority_fee, gwei(2));
+        assert_eq!(fees.max_fee, gwei(62));
+        // A configured price above the floor is kept as the priority fee
+        let fees = floored_fees(gwei(5), recent(30, 2), gwei(100));
+        assert_eq!((fees.priority_fee, fees.max_fee), (gwei(5), gwei(65)));
+        let fees = floored_fees(gwei(1), recent(60, 200), gwei(100));
+        assert_eq!((fees.priority_fee, fees.max_fee), (gwei(100), gwei(100)));
+    }
+
+    #[test]
+    fn eip1559_fees_set_separate_max_and_priority_fees() {
+        let fees = Fees {
+            max_fee: U256::from(62),
+            priority_fee: U256::from(2),
+        };
+        let tx = with_fees(TransactionRequest::default(), fees, false);
+        assert_eq!(tx.max_fee_per_gas, Some(62));
+        assert_eq!(tx.max_priority_fee_per_gas, Some(2));
+        assert_eq!(tx.gas_price, None);
+        let tx = with_fees(TransactionRequest::default(), fees, true);
+        assert_eq!(tx.gas_price, Some(62));
+    }
+
+    #[actix_web::test]
+    async fn body_errors_are_padded_to_the_minimum_response_time() {
+        let mut state = test_state("http://127.0.0.1:1");
+        state.min_response_time = Some(Duration::from_millis(300));
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(web::Data::new(state))
+                .service(faucet_resource()),
+        )
+        .await;
+
+        let started = Instant::now();
+        let req = actix_web::test::TestRequest::post()
+            .uri("/faucet")
+            .insert_header(ContentType::json())
+            .set_payload("{not json")
+            .to_request();
+        let response = actix_web::test::call_service(&app, req).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(started.elapsed() >= Duration::from_millis(300));
+    }
+
+    #[test]
+    fn runway_of_free_drips_is_unbounded() {
+        let runway = |balance: u64, amount: u64, gas_cost: u64| {
+            drip_runway(
+                U256::from(balance),
+                U256::from(amount),
+                U256::from(gas_cost),
+            )
+        };
+        assert_eq!(runway(100, 0, 0), None);
+        assert_eq!(runway(100, 0, 30), Some(U256::from(3)));
+        assert_eq!(runway(100, 45, 5), Some(U256::from(2)));
+    }
+
+    #[actix_web::test]
+    async fn cost_estimate_of_free_drips_has_no_runway() {
+        let rpc_url = mock_rpc(Arc::new(|method, _| match method {
+            "eth_getBalance" => Ok(json!("0x64")),
+            _ => Err(format!("unexpected {}", method)),
+        }));
+        let mut state = test_state(&rpc_url);
+        state.enable_cost_estimate = true;
+        state.tokens_per_request = U256::ZERO;
+        state.gas_price = U256::ZERO;
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(web::Data::new(state))
+                .route("/cost-estimate", web::get().to(cost_estimate)),
+        )
+        .await;
+
+        let req = actix_web::test::TestRequest::get()
+            .uri("/cost-estimate")
+            .to_request();
+        let body: Value = actix_web::test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["drips_remaining"], Value::Null);
+        assert_eq!(body["balance_wei"], "100");
+    }
+
+    #[actix_web::test]
+    async fn failed_send_frees_the_interval_slot() {
+        let mut state = test_state(&funded_node(10_000_000_000_000_000_000, |_| {
+            Err("nonce too low".to_string())
+        }));
+        state.interval_gate = IntervalGate::new(Duration::from_secs(60));
+
+        assert!(dispense(&drip_request(), None, &state).await.is_err());
+        assert!(state.interval_gate.try_pass().is_ok());
+    }
+
+    #[actix_web::test]
+    async fn sent_drip_holds_the_interval_slot() {
+        let sent = SentRaw::default();
+        let node = sent.clone();
+        let mut state = test_state(&funded_node(10_000_000_000_000_000_000, move |params| {
+            node.accept(params)
+        }));
+        state.interval_gate = IntervalGate::new(Duration::from_secs(60));
+
+        assert!(dispense(&drip_request(), None, &state).await.is_ok());
+        let other = request(json!({ "address": Address::with_last_byte(3).to_string() }));
+        let error = dispense(&other, None, &state).await.err().unwrap();
+        assert_eq!(error.code, "FAUCET_BUSY");
+    }
+
+    #[actix_web::test]
+    async fn pending_drip_conflict_carries_the_transaction_hash() {
+        let sent = SentRaw::default();
+        let node = sent.clone();
+        let state = test_state(&funded_node(10_000_000_000_000_000_000, move |params| {
+            node.accept(params)
+        }));
+
+        let drip = dispense(&drip_request(), None, &state).await.ok().unwrap();
+        let error = dispense(&drip_request(), None, &state).await.err().unwrap();
+        assert_eq!(error.status, StatusCode::CONFLICT);
+
+        let body = actix_web::body::to_bytes(error.render(ResponseVersion::V2).into_body())
+            .await
+            .unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"]["code"], "DRIP_PENDING");
+        assert_eq!(
+            body["error"]["transaction_hash"],
+            format!("{:?}", drip.tx_hash)
+        );
+    }
+
+    #[actix_web::test]
+    async fn watcher_resends_an_out_of_gas_drip_and_rerecords_its_claim() {
+        let reverted = TxHash::with_last_byte(1);
+        let sent = SentRaw::default();
+        let node = sent.clone();
+        let url = mock_rpc(Arc::new(move |method, params| match method {
+            "eth_getTransactionReceipt" if params[0] == json!(reverted) => {
+                Ok(receipt("0x0", 21_000))
+            }
+            "eth_getTransactionReceipt" => Ok(receipt("0x1", 30_000)),
+            "eth_getTransactionCount" => Ok(json!("0x1")),
+            "eth_chainId" => Ok(json!("0x1")),
+            "eth_sendRawTransaction" => node.accept(params),
+            _ => Err(format!("unexpected {}", method)),
+        }));
+        let mut state = test_state(&url);
+        state.max_gas_bumps = 1;
+        let drip = sent_drip(&state, reverted);
+        let to = drip.to;
+        state.store.record_claim(&drip.claim).await.unwrap();
+        state
+            .pending
+            .try_reserve(to)
+            .ok()
+            .unwrap()
+            .submitted(reverted);
+        let state = web::Data::new(state);
+
+        watch_pending(state.clone(), drip).await;
+
+        let raw = sent.list();
+        assert_eq!(raw.len(), 1);
+        let resent = hex::decode(&raw[0][2..]).unwrap();
+        type TxEnvelope = <Ethereum as Network>::TxEnvelope;
+        let TxEnvelope::Eip1559(resent) = TxEnvelope::decode_2718(&mut resent.as_slice()).unwrap()
+        else {
+            panic!("expected an EIP-1559 transaction");
+        };
+        assert_eq!(resent.tx().gas_limit, 42_000);
+
+        let claims = state.store.history(to, 10).await.unwrap();
+        assert_eq!(claims.len(), 1);
+        assert_eq!(claims[0].tx_hash, format!("{:?}", resent.hash()));
+        assert_eq!(state.pending.len(), 0);
+    }
+
+    #[test]
+    fn retention_too_long_for_a_timestamp_is_out_of_range() {
+        assert_eq!(retention_secs(0), Some(0));
+        assert_eq!(retention_secs(365), Some(365 * 24 * 60 * 60));
+        assert_eq!(retention_secs(i64::MAX as u64 / (24 * 60 * 60) + 1), None);
+        assert_eq!(retention_secs(u64::MAX), None);
+    }
+}
@@ -0,0 +1,48 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use alloy_primitives::U64;
+use alloy_provider::Provider;
+use serde::Deserialize;
+
+use crate::FaucetProvider;
+
+/// How long an observed mempool size is reused before querying txpool_status again.
+const MEMPOOL_CACHE_TTL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Deserialize)]
+struct TxpoolStatus {
+    pending: U64,
+}
+
+/// Watches the node's pending transaction pool through txpool_status.
+#[derive(Default)]
+pub struct MempoolMonitor {
+    observed: Mutex<Option<(Instant, u64)>>,
+}
+
+impl MempoolMonitor {
+    /// Number of pending transactions in the node's pool, served from cache while fresh.
+    pub async fn pending(&self, provider: &FaucetProvider) -> Result<u64, String> {
+        if let Some((fetched_at, pending)) = *self.observed.lock().unwrap() {
+            if fetched_at.elapsed() < MEMPOOL_CACHE_TTL {
+                return Ok(pending);
+            }
+        }
+
+        let status: TxpoolStatus = provider
+            .raw_request("txpool_status".into(), ())
+            .await
+            .map_err(|e| e.to_string())?;
+        let pending = status.pending.to::<u64>();
+        *self.observed.lock().unwrap() = Some((Instant::now(), pending));
+        Ok(pending)
+    }
+
+    /// The most recently observed pool size, however old.
+    pub fn last_observed(&self) -> Option<u64> {
+        self.observed.lock().unwrap().map(|(_, pending)| pending)
+    }
+}
@@ -0,0 +1,195 @@
+use std::{collections::BTreeSet, sync::Mutex};
+
+use tracing::warn;
+
+#[derive(Default)]
+struct NonceState {
+    /// Next nonce never handed out, `None` until the first reservation
+    next: Option<u64>,
+    /// Nonces below `next` whose transaction was never sent or was dropped
+    released: BTreeSet<u64>,
+    /// Nonces reserved for a transaction that is still being sent
+    in_flight: BTreeSet<u64>,
+}
+
+/// Hands out faucet nonces so concurrent drips don't collide, reconciled with the node on each use.
+#[derive(Default)]
+pub struct NonceTracker {
+    state: Mutex<NonceState>,
+    /// Follow the node down to a lower pending nonce, reusing nonces it seems to have lost
+    resync_down: bool,
+}
+
+/// A nonce held for one transaction, handed back for reuse when dropped without being committed.
+pub struct NonceReservation<'a> {
+    tracker: &'a NonceTracker,
+    pub nonce: u64,
+}
+
+impl NonceTracker {
+    pub fn new(resync_down: bool) -> Self {
+        Self {
+            state: Mutex::default(),
+            resync_down,
+        }
+    }
+
+    /// Reserves the next nonce given the node's pending transaction count.
+    ///
+    /// A node count below the local one can mean the node lost its mempool and dropped our
+    /// transactions, but they may still be in other nodes' mempools, so by default the higher
+    /// local nonce is kept and a warning logged. With `resync_down`, the local nonce follows the
+    /// node down once no transaction at or above the node's count is still being sent, so later
+    /// drips aren't stuck behind the gap.
+    pub fn reserve(&self, node_pending: u64) -> NonceReservation<'_> {
+        let mut state = self.state.lock().unwrap();
+        state.released = state.released.split_off(&node_pending);
+
+        if let Some(next) = state.next.filter(|next| *next > node_pending) {
+            if !self.resync_down {
+                warn!(
+                    "node reports pending nonce {} below local nonce {}, transactions may have been dropped, keeping the local nonce",
+                    node_pending, next
+                );
+            } else if state.in_flight.range(node_pending..).next().is_none() {
+                warn!(
+                    "node reports pending nonce {} below local nonce {}, transactions were dropped, resyncing to the node",
+                    node_pending, next
+                );
+                state.next = Some(node_pending);
+                state.released.clear();
+            } else {
+                warn!(
+                    "node reports pending nonce {} below local nonce {} while transactions are being sent, keeping the local nonce",
+                    node_pending, next
+                );
+            }
+        }
+
+        let nonce = match state.released.pop_first() {
+            Some(nonce) => nonce,
+            None => state.next.unwrap_or_default().max(node_pending),
+        };
+        state.next = Some(state.next.unwrap_or_default().max(nonce + 1));
+        state.in_flight.insert(nonce);
+
+        NonceReservation {
+            tracker: self,
+            nonce,
+        }
+    }
+
+    /// Hands back the nonce of a committed transaction the node no longer knows when `resync_down`
+    /// is set, so the next drip fills the gap instead of queueing behind it.
+    pub fn dropped(&self, nonce: u64) {
+        if !self.resync_down {
+            warn!(
+                "nonce {} was dropped by the node, later drips queue behind it until it is mined",
+                nonce
+            );
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        if state.next.is_some_and(|next| nonce < next) && !state.in_flight.contains(&nonce) {
+            state.released.insert(nonce);
+        }
+    }
+}
+
+impl NonceReservation<'_> {
+    /// Marks the nonce as used by a broadcast transaction, returning it.
+    pub fn commit(self) -> u64 {
+        let nonce = self.nonce;
+        self.tracker.state.lock().unwrap().in_flight.remove(&nonce);
+        std::mem::forget(self);
+        nonce
+    }
+}
+
+impl Drop for NonceReservation<'_> {
+    fn drop(&mut self) {
+        let mut state = self.tracker.state.lock().unwrap();
+        state.in_flight.remove(&self.nonce);
+        state.released.insert(self.nonce);
+        // Shrink back over released nonces at the top so no gap is left behind
+        while let Some(next) = state.next.filter(|next| *next > 0) {
+            if !state.released.remove(&(next - 1)) {
+                break;
+            }
+            state.next = Some(next - 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_the_node_count() {
+        let nonces = NonceTracker::default();
+        nonces.reserve(4).commit();
+        nonces.reserve(5).commit();
+        assert_eq!(nonces.reserve(6).nonce, 6);
+    }
+
+    #[test]
+    fn concurrent_reservations_get_distinct_nonces() {
+        let nonces = NonceTracker::default();
+        let first = nonces.reserve(3);
+        let second = nonces.reserve(3);
+        assert_eq!((first.nonce, second.nonce), (3, 4));
+    }
+
+    #[test]
+    fn uncommitted_nonce_is_reused() {
+        let nonces = NonceTracker::default();
+        let first = nonces.reserve(0);
+        let second = nonces.reserve(0);
+        drop(first);
+        second.commit();
+        assert_eq!(nonces.reserve(0).nonce, 0);
+    }
+
+    #[test]
+    fn lower_node_count_keeps_the_higher_local_nonce() {
+        let nonces = NonceTracker::default();
+        for pending in 10..13 {
+            nonces.reserve(pending).commit();
+        }
+        // The node restarted and lost 11 and 12, which other nodes may still have
+        assert_eq!(nonces.reserve(11).commit(), 13);
+        nonces.dropped(12);
+        assert_eq!(nonces.reserve(11).nonce, 14);
+    }
+
+    #[test]
+    fn lower_node_count_resyncs_down_when_enabled() {
+        let nonces = NonceTracker::new(true);
+        for pending in 10..13 {
+            nonces.reserve(pending).commit();
+        }
+        assert_eq!(nonces.reserve(11).nonce, 11);
+    }
+
+    #[test]
+    fn lower_node_count_keeps_local_nonce_while_sending() {
+        let nonces = NonceTracker::new(true);
+        nonces.reserve(10).commit();
+        let sending = nonces.reserve(11);
+        // The node hasn't seen 11 yet
+        assert_eq!(nonces.reserve(11).nonce, 12);
+        sending.commit();
+    }
+
+    #[test]
+    fn dropped_nonce_is_reused_while_sending() {
+        let nonces = NonceTracker::new(true);
+        nonces.reserve(7).commit();
+        nonces.reserve(8).commit();
+        let sending = nonces.reserve(9);
+        nonces.dropped(7);
+        assert_eq!(nonces.reserve(7).nonce, 7);
+        sending.commit();
+    }
+}
@@ -0,0 +1,171 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use alloy_primitives::{Address, TxHash};
+
+/// Drips that have been started but not yet confirmed, keyed by recipient.
+pub struct PendingDrips {
+    max_pending: usize,
+    /// `None` while the transaction is still being sent
+    drips: Mutex<HashMap<Address, Option<TxHash>>>,
+}
+
+pub enum ReserveError {
+    /// A drip to the recipient is pending, with its hash once sent
+    Pending(Option<TxHash>),
+    /// Too many drips are pending overall
+    Full,
+}
+
+/// Holds a recipient's pending slot, releasing it when dropped unless the drip was submitted.
+pub struct PendingReservation<'a> {
+    pending: &'a PendingDrips,
+    address: Address,
+}
+
+impl PendingDrips {
+    pub fn new(max_pending: usize) -> Self {
+        Self {
+            max_pending,
+            drips: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Reserves `address` unless a drip to it is already pending or the tracker is full.
+    pub fn try_reserve(&self, address: Address) -> Result<PendingReservation<'_>, ReserveError> {
+        let mut drips = self.drips.lock().unwrap();
+        if let Some(tx_hash) = drips.get(&address) {
+            return Err(ReserveError::Pending(*tx_hash));
+        }
+        if drips.len() >= self.max_pending {
+            return Err(ReserveError::Full);
+        }
+        drips.insert(address, None);
+        Ok(PendingReservation {
+            pending: self,
+            address,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.drips.lock().unwrap().len()
+    }
+
+    pub fn max_pending(&self) -> usize {
+        self.max_pending
+    }
+
+    /// Points a pending recipient at the transaction that replaced its drip.
+    pub fn resubmitted(&self, address: Address, tx_hash: TxHash) {
+        self.drips.lock().unwrap().insert(address, Some(tx_hash));
+    }
+
+    /// Releases `address` once its drip is confirmed or dropped.
+    pub fn clear(&self, address: Address) {
+        self.drips.lock().unwrap().remove(&address);
+    }
+}
+
+impl PendingReservation<'_> {
+    /// Keeps the slot after the transaction is broadcast, until `PendingDrips::clear`.
+    pub fn submitted(self, tx_hash: TxHash) {
+        self.pending
+            .drips
+            .lock()
+            .unwrap()
+            .insert(self.address, Some(tx_hash));
+        std::mem::forget(self);
+    }
+}
+
+impl Drop for PendingReservation<'_> {
+    fn drop(&mut self) {
+        self.pending.clear(self.address);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(byte: u8) -> Address {
+        Address::with_last_byte(byte)
+    }
+
+    #[test]
+    fn second_drip_to_a_pending_recipient_is_refused() {
+        let pending = PendingDrips::new(10);
+        let sending = pending.try_reserve(address(1)).ok().unwrap();
+        assert!(matches!(
+            pending.try_reserve(address(1)),
+            Err(ReserveError::Pending(None))
+        ));
+
+        let tx_hash = TxHash::with_last_byte(7);
+        sending.submitted(tx_hash);
+        assert!(matches!(
+            pending.try_reserve(address(1)),
+            Err(ReserveError::Pending(Some(hash))) if hash == tx_hash
+        ));
+        assert_eq!(pending.len(), 1);
+    }
+
+    #[test]
+    fn other_recipients_are_tracked_concurrently() {
+        let pending = PendingDrips::new(10);
+        let first = pending.try_reserve(address(1)).ok().unwrap();
+        let second = pending.try_reserve(address(2)).ok().unwrap();
+        first.submitted(TxHash::with_last_byte(1));
+        second.submitted(TxHash::with_last_byte(2));
+        assert_eq!(pending.len(), 2);
+    }
+
+    #[test]
+    fn cap_refuses_new_recipients() {
+        let pending = PendingDrips::new(2);
+        pending
+            .try_reserve(address(1))
+            .ok()
+            .unwrap()
+            .submitted(TxHash::ZERO);
+        pending
+            .try_reserve(address(2))
+            .ok()
+            .unwrap()
+            .submitted(TxHash::ZERO);
+        assert!(matches!(
+            pending.try_reserve(address(3)),
+            Err(ReserveError::Full)
+        ));
+
+        pending.clear(address(1));
+        assert!(pending.try_reserve(address(3)).is_ok());
+    }
+
+    #[test]
+    fn unsent_reservation_is_released_on_drop() {
+        let pending = PendingDrips::new(10);
+        drop(pending.try_reserve(address(1)));
+        assert_eq!(pending.len(), 0);
+        assert!(pending.try_reserve(address(1)).is_ok());
+    }
+
+    #[test]
+    fn clear_and_resubmit() {
+        let pending = PendingDrips::new(10);
+        pending
+            .try_reserve(address(1))
+            .ok()
+            .unwrap()
+            .submitted(TxHash::ZERO);
+
+        let replacement = TxHash::with_last_byte(9);
+        pending.resubmitted(address(1), replacement);
+        assert!(matches!(
+            pending.try_reserve(address(1)),
+            Err(ReserveError::Pending(Some(hash))) if hash == replacement
+        ));
+
+        pending.clear(address(1));
+        assert_eq!(pending.len(), 0);
+    }
+}
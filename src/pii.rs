@@ -0,0 +1,46 @@
+use std::net::{IpAddr, Ipv6Addr};
+
+use alloy_primitives::{keccak256, Address, TxHash};
+
+/// Replaces recipients, IPs and transaction hashes with salted hashes before they are stored.
+///
+/// Hashes keep the type they replace so the claim store needs no separate schema: cooldowns and
+/// per-IP limits work unchanged on pseudonyms, while the stored values can't be linked back to
+/// a recipient without the salt.
+pub struct Pseudonymizer {
+    salt: Vec<u8>,
+}
+
+impl Pseudonymizer {
+    pub fn new(salt: &[u8]) -> Self {
+        Self {
+            salt: salt.to_vec(),
+        }
+    }
+
+    fn hash(&self, kind: &[u8], value: &[u8]) -> [u8; 32] {
+        let mut input = Vec::with_capacity(self.salt.len() + kind.len() + value.len());
+        input.extend_from_slice(&self.salt);
+        input.extend_from_slice(kind);
+        input.extend_from_slice(value);
+        keccak256(input).0
+    }
+
+    pub fn address(&self, address: Address) -> Address {
+        Address::from_slice(&self.hash(b"address", address.as_slice())[12..])
+    }
+
+    pub fn ip(&self, ip: IpAddr) -> IpAddr {
+        let ip = match ip.to_canonical() {
+            IpAddr::V4(ip) => ip.octets().to_vec(),
+            IpAddr::V6(ip) => ip.octets().to_vec(),
+        };
+        let hash = self.hash(b"ip", &ip);
+        IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(&hash[..16]).unwrap()))
+    }
+
+    /// Transaction hashes are pseudonymized too, the chain would otherwise reveal the recipient.
+    pub fn tx_hash(&self, tx_hash: TxHash) -> String {
+        format!("{:?}", TxHash::from(self.hash(b"tx", tx_hash.as_slice())))
+    }
+}
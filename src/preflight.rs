@@ -0,0 +1,224 @@
+use alloy_primitives::U256;
+use alloy_provider::Provider;
+use tracing::{error, info, warn};
+
+use crate::{
+    default_drip_fees, drip_runway, format_units, AppState, NATIVE_DECIMALS, WEI_PER_GWEI,
+};
+
+/// Tracing target the report is logged under, e.g. `RUST_LOG=preflight=info`.
+pub const PREFLIGHT_TARGET: &str = "preflight";
+
+/// Intrinsic gas of a plain transfer, no drip can use less.
+const MIN_GAS_LIMIT: u64 = 21_000;
+
+#[derive(Clone, Copy)]
+enum Outcome {
+    Ok,
+    Warning,
+    Fatal,
+}
+
+/// Startup checks, logged one line per check so they can be read or filtered as a whole.
+struct Report {
+    fatal: usize,
+}
+
+impl Report {
+    fn check(&mut self, check: &str, outcome: Outcome, detail: String) {
+        match outcome {
+            Outcome::Ok => info!(target: PREFLIGHT_TARGET, check, status = "ok", "{}", detail),
+            Outcome::Warning => {
+                warn!(target: PREFLIGHT_TARGET, check, status = "warning", "{}", detail)
+            }
+            Outcome::Fatal => {
+                self.fatal += 1;
+                error!(target: PREFLIGHT_TARGET, check, status = "fatal", "{}", detail)
+            }
+        }
+    }
+}
+
+/// Runs the pre-flight checks against the configured node, returning the number of fatal issues.
+pub async fn run(state: &AppState, rpc_url: &str, chain_id: u64) -> usize {
+    let mut report = Report { fatal: 0 };
+    let address = state.wallet.default_signer().address();
+
+    report.check(
+        "key",
+        Outcome::Ok,
+        format!("private key loaded, faucet address {}", address),
+    );
+    report.check(
+        "rpc",
+        Outcome::Ok,
+        format!("{} reachable, chain id {}", rpc_url, chain_id),
+    );
+
+    // Gas settings
+    let gas_price_gwei = state.gas_price / U256::from(WEI_PER_GWEI);
+    let limit_too_low = state.gas_limit < U256::from(MIN_GAS_LIMIT);
+    let price_out_of_range = gas_price_gwei < U256::from(state.min_gas_price_gwei)
+        || gas_price_gwei > U256::from(state.max_gas_price_gwei);
+    if limit_too_low {
+        report.check(
+            "gas",
+            Outcome::Fatal,
+            format!(
+                "gas limit {} is below the {} needed for a transfer",
+                state.gas_limit, MIN_GAS_LIMIT
+            ),
+        );
+    }
+    if price_out_of_range {
+        report.check(
+            "gas",
+            Outcome::Warning,
+            format!(
+                "gas price {} gwei is outside the bid range {}-{} gwei",
+                gas_price_gwei, state.min_gas_price_gwei, state.max_gas_price_gwei
+            ),
+        );
+    }
+    if !limit_too_low && !price_out_of_range {
+        report.check(
+            "gas",
+            Outcome::Ok,
+            format!(
+                "gas limit {}, gas price {} gwei",
+                state.gas_limit,
+                format_units(state.gas_price, 9)
+            ),
+        );
+    }
+    match state.provider.get_gas_price().await {
+        Ok(node_price) if U256::from(node_price) > state.gas_price => report.check(
+            "gas",
+            Outcome::Warning,
+            format!(
+                "node gas price {} gwei is above the configured {} gwei, drips may not be mined",
+                format_units(U256::from(node_price), 9),
+                format_units(state.gas_price, 9)
+            ),
+        ),
+        Ok(_) => {}
+        Err(e) => report.check(
+            "gas",
+            Outcome::Warning,
+            format!("could not read the node gas price: {}", e),
+        ),
+    }
+
+    // Balance and how long it lasts
+    if state.tokens_per_request.is_zero() {
+        report.check(
+            "amount",
+            Outcome::Fatal,
+            "--tokens-per-request is zero".to_string(),
+        );
+    }
+    match state.balance.get(&state.provider, address).await {
+        Ok(balance) => {
+            let (gas_limit, gas_price) = default_drip_fees(state).await;
+            match drip_runway(balance, state.tokens_per_request, gas_limit * gas_price) {
+                Some(runway) => report.check(
+                    "balance",
+                    if runway.is_zero() {
+                        Outcome::Warning
+                    } else {
+                        Outcome::Ok
+                    },
+                    format!(
+                        "balance {}, enough for about {} drips of {}",
+                        format_units(balance, NATIVE_DECIMALS),
+                        runway,
+                        format_units(state.tokens_per_request, NATIVE_DECIMALS)
+                    ),
+                ),
+                None => report.check(
+                    "balance",
+                    Outcome::Ok,
+                    format!(
+                        "balance {}, runway n/a as drips cost nothing",
+                        format_units(balance, NATIVE_DECIMALS)
+                    ),
+                ),
+            }
+        }
+        Err(e) => report.check(
+            "balance",
+            Outcome::Fatal,
+            format!("could not read the faucet balance: {}", e),
+        ),
+    }
+
+    report.check("features", Outcome::Ok, enabled_features(state).join(", "));
+
+    report.fatal
+}
+
+fn enabled_features(state: &AppState) -> Vec<&'static str> {
+    let features = [
+        ("amount-presets", !state.amount_presets.is_empty()),
+        ("usd-amount", state.price_oracle.is_some()),
+        ("partial-drip", state.allow_partial_drip),
+        ("priority-fee-floor", state.priority_fee_floor.is_some()),
+        ("estimate-gas", state.estimate_gas),
+        ("gas-bumps", state.max_gas_bumps > 0),
+        ("cooldown", state.cooldown_secs > 0),
+        ("addresses-per-ip", state.max_addresses_per_ip.is_some()),
+        ("eligibility-service", state.eligibility.is_some()),
+        ("known-addresses", !state.known_addresses.is_empty()),
+        ("recipient-prefixes", !state.recipient_prefixes.is_empty()),
+        ("anonymizer-ranges", !state.anonymizer_ranges.is_empty()),
+        ("require-origin", state.require_origin),
+        ("block-age-check", state.max_block_age_secs.is_some()),
+        ("mempool-check", state.max_mempool_pending.is_some()),
+        ("broadcast-to-all", !state.broadcast_targets.is_empty()),
+        ("private-relay", state.private_relay.is_some()),
+        ("verify-broadcast", state.verify_broadcast),
+        ("explorer", state.explorer.is_some()),
+        (
+            "calldata-tag",
+            state.tag_calldata && state.faucet_id.is_some(),
+        ),
+        ("rpc-proxy", state.rpc_proxy.is_some()),
+        ("cost-estimate", state.enable_cost_estimate),
+        ("signed-responses", state.response_signer.is_some()),
+        ("constant-time-response", state.min_response_time.is_some()),
+        ("no-store-pii", state.no_store_pii),
+    ];
+    let enabled: Vec<_> = features
+        .into_iter()
+        .filter_map(|(name, enabled)| enabled.then_some(name))
+        .collect();
+    if enabled.is_empty() {
+        vec!["none"]
+    } else {
+        enabled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::tests::{mock_rpc, test_state};
+
+    #[actix_web::test]
+    async fn free_drips_are_fatal_without_panicking() {
+        let rpc_url = mock_rpc(Arc::new(|method, _| match method {
+            "eth_gasPrice" => Ok(json!("0x0")),
+            "eth_getBalance" => Ok(json!("0x64")),
+            _ => Err(format!("unexpected {}", method)),
+        }));
+        let mut state = test_state(&rpc_url);
+        state.tokens_per_request = U256::ZERO;
+        state.gas_price = U256::ZERO;
+
+        assert_eq!(run(&state, &rpc_url, 1).await, 1);
+    }
+}
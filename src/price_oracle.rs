@@ -0,0 +1,124 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use alloy_primitives::U256;
+use serde_json::Value;
+use tracing::warn;
+
+/// How long a fetched price is reused before querying the oracle again.
+const PRICE_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Converts a fixed USD amount into token units using an HTTP price feed.
+pub struct PriceOracle {
+    client: reqwest::Client,
+    url: String,
+    /// Dotted path to the USD price in the oracle response, e.g. `ethereum.usd`
+    price_path: String,
+    usd_amount: f64,
+    /// Most that is dispensed however low the feed price, so a glitched feed can't drain the faucet
+    max_amount: U256,
+    cached: Mutex<Option<(Instant, f64)>>,
+}
+
+impl PriceOracle {
+    pub fn new(url: String, price_path: String, usd_amount: f64, max_amount: U256) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+            price_path,
+            usd_amount,
+            max_amount,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// USD price of one whole token, served from cache while fresh.
+    pub async fn price(&self) -> Result<f64, String> {
+        if let Some((fetched_at, price)) = *self.cached.lock().unwrap() {
+            if fetched_at.elapsed() < PRICE_CACHE_TTL {
+                return Ok(price);
+            }
+        }
+
+        let response: Value = self
+            .client
+            .get(&self.url)
+            .timeout(Duration::from_secs(5))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| e.to_string())?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let price = self
+            .price_path
+            .split('.')
+            .try_fold(&response, |value, key| value.get(key))
+            .and_then(Value::as_f64)
+            .filter(|price| price.is_finite() && *price > 0.0)
+            .ok_or_else(|| format!("no positive price at {}", self.price_path))?;
+
+        *self.cached.lock().unwrap() = Some((Instant::now(), price));
+        Ok(price)
+    }
+
+    /// Amount in base units (with `decimals`) worth the configured USD amount at `price`, capped
+    /// at the configured maximum. `None` when the amount rounds down to nothing.
+    pub fn amount_at(&self, price: f64, decimals: u8) -> Option<U256> {
+        let tokens = self.usd_amount / price;
+        let amount = U256::from((tokens * 10f64.powi(decimals as i32)) as u128);
+        if amount.is_zero() {
+            return None;
+        }
+        if amount > self.max_amount {
+            warn!(
+                "price {} gives {} base units for ${}, capping at {}",
+                price, amount, self.usd_amount, self.max_amount
+            );
+            return Some(self.max_amount);
+        }
+        Some(amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn oracle(usd_amount: f64, max_amount: u64) -> PriceOracle {
+        PriceOracle::new(
+            "http://localhost".to_string(),
+            "price".to_string(),
+            usd_amount,
+            U256::from(max_amount),
+        )
+    }
+
+    #[test]
+    fn converts_usd_to_base_units() {
+        assert_eq!(
+            oracle(5.0, u64::MAX).amount_at(2.0, 6),
+            Some(U256::from(2_500_000))
+        );
+    }
+
+    #[test]
+    fn caps_amount_at_tiny_prices() {
+        let oracle = oracle(5.0, 1_000);
+        assert_eq!(oracle.amount_at(1e-30, 18), Some(U256::from(1_000)));
+        assert_eq!(
+            oracle.amount_at(f64::MIN_POSITIVE, 18),
+            Some(U256::from(1_000))
+        );
+    }
+
+    #[test]
+    fn amount_below_one_base_unit_is_none() {
+        // $0.01 of a token worth $1e9 at 6 decimals is 1e-5 base units
+        assert_eq!(oracle(0.01, u64::MAX).amount_at(1e9, 6), None);
+    }
+}
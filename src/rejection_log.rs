@@ -0,0 +1,69 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    net::IpAddr,
+    path::Path,
+    sync::Mutex,
+};
+
+use serde::Serialize;
+use tracing::{info, warn};
+
+/// Tracing target rejections are logged under, e.g. `RUST_LOG=rejections=info`.
+pub const REJECTION_TARGET: &str = "rejections";
+
+/// Longest recipient string recorded, requests may carry arbitrary text there.
+const MAX_RECIPIENT_LEN: usize = 64;
+
+#[derive(Serialize)]
+struct Rejection<'a> {
+    timestamp: String,
+    code: &'a str,
+    recipient: Option<&'a str>,
+    ip: Option<IpAddr>,
+}
+
+/// Per-event record of rejected /faucet requests, kept apart from successful drips.
+#[derive(Default)]
+pub struct RejectionLog {
+    /// JSON lines file that rejections are appended to, in addition to the tracing target
+    file: Option<Mutex<File>>,
+}
+
+impl RejectionLog {
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Some(Mutex::new(file)),
+        })
+    }
+
+    /// Records a rejection by its code only, request bodies and secrets are never logged.
+    pub fn record(&self, code: &str, recipient: Option<&str>, ip: Option<IpAddr>) {
+        let recipient = recipient.map(|r| match r.char_indices().nth(MAX_RECIPIENT_LEN) {
+            Some((end, _)) => &r[..end],
+            None => r,
+        });
+        info!(
+            target: REJECTION_TARGET,
+            code,
+            recipient,
+            ip = ip.map(|ip| ip.to_string()),
+            "request rejected"
+        );
+
+        if let Some(file) = &self.file {
+            let rejection = Rejection {
+                timestamp: chrono::Utc::now().to_rfc3339(),
+                code,
+                recipient,
+                ip,
+            };
+            let mut line = serde_json::to_string(&rejection).unwrap_or_default();
+            line.push('\n');
+            if let Err(e) = file.lock().unwrap().write_all(line.as_bytes()) {
+                warn!("failed to write rejection log: {}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use std::time::Duration;
+
+use alloy_primitives::{keccak256, Bytes, TxHash};
+use alloy_signer::SignerSync;
+use alloy_signer_local::PrivateKeySigner;
+use serde_json::{json, Value};
+
+/// Header carrying the request signature that identifies the searcher to Flashbots-style relays.
+const SIGNATURE_HEADER: &str = "x-flashbots-signature";
+
+/// Submits signed transactions to a private relay so they skip the public mempool.
+pub struct PrivateRelay {
+    client: reqwest::Client,
+    url: String,
+    signer: PrivateKeySigner,
+}
+
+impl PrivateRelay {
+    pub fn new(url: String, signer: PrivateKeySigner) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+            signer,
+        }
+    }
+
+    /// Sends `raw` with eth_sendPrivateTransaction, returning the hash the relay reports for it.
+    pub async fn send(&self, tx_hash: TxHash, raw: &Bytes) -> Result<TxHash, String> {
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_sendPrivateTransaction",
+            "params": [{ "tx": raw }],
+        })
+        .to_string();
+        let digest = format!("0x{}", hex::encode(keccak256(body.as_bytes())));
+        let signature = self
+            .signer
+            .sign_message_sync(digest.as_bytes())
+            .map_err(|e| e.to_string())?;
+
+        let response: Value = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(
+                SIGNATURE_HEADER,
+                format!(
+                    "{}:0x{}",
+                    self.signer.address(),
+                    hex::encode(signature.as_bytes())
+                ),
+            )
+            .body(body)
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| e.to_string())?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if let Some(error) = response.get("error") {
+            let message = error.get("message").and_then(Value::as_str);
+            return Err(message.map_or_else(|| error.to_string(), str::to_string));
+        }
+        // Relays answer with the hash itself, an object holding it, or nothing useful at all
+        let result = response.get("result").unwrap_or(&Value::Null);
+        let reported = result
+            .as_str()
+            .or_else(|| result.get("txHash").and_then(Value::as_str))
+            .or_else(|| result.get("hash").and_then(Value::as_str))
+            .and_then(|hash| hash.parse().ok());
+        Ok(reported.unwrap_or(tx_hash))
+    }
+}
@@ -0,0 +1,114 @@
+use actix_web::{
+    http::{header::RETRY_AFTER, StatusCode},
+    HttpRequest, HttpResponse,
+};
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::json;
+
+use crate::ErrorResponse;
+
+/// Request header selecting the response schema.
+pub const ACCEPT_VERSION: &str = "accept-version";
+
+/// Response schema spoken to a client.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ResponseVersion {
+    /// The flat shape: the result itself on success, `{"error": "..."}` on failure
+    V1,
+    /// An `{"ok": ..., "data" | "error": ...}` envelope with machine readable error codes
+    V2,
+}
+
+impl ResponseVersion {
+    /// The version named by the `Accept-Version` header, or `default` without one.
+    pub fn from_request(
+        req: &HttpRequest,
+        default: ResponseVersion,
+    ) -> Result<ResponseVersion, FaucetError> {
+        let Some(header) = req.headers().get(ACCEPT_VERSION) else {
+            return Ok(default);
+        };
+
+        match header.to_str().map(|v| v.trim().to_ascii_lowercase()) {
+            Ok(v) if v == "1" || v == "v1" => Ok(ResponseVersion::V1),
+            Ok(v) if v == "2" || v == "v2" => Ok(ResponseVersion::V2),
+            _ => Err(FaucetError::new(
+                StatusCode::BAD_REQUEST,
+                "UNSUPPORTED_VERSION",
+                "Unsupported Accept-Version, expected 1 or 2",
+            )),
+        }
+    }
+}
+
+/// A rejected request, rendered according to the client's response version.
+#[derive(Debug)]
+pub struct FaucetError {
+    pub status: StatusCode,
+    /// Stable identifier for the failure, only exposed in v2 responses
+    pub code: &'static str,
+    pub message: String,
+    /// Seconds after which the request may succeed, sent as Retry-After
+    pub retry_after: Option<u64>,
+    /// Transaction the failure refers to, only exposed in v2 responses
+    pub transaction_hash: Option<String>,
+}
+
+impl FaucetError {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+            retry_after: None,
+            transaction_hash: None,
+        }
+    }
+
+    pub fn with_retry_after(mut self, secs: u64) -> Self {
+        self.retry_after = Some(secs);
+        self
+    }
+
+    pub fn with_transaction_hash(mut self, tx_hash: impl Into<String>) -> Self {
+        self.transaction_hash = Some(tx_hash.into());
+        self
+    }
+
+    pub fn render(&self, version: ResponseVersion) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status);
+        if let Some(secs) = self.retry_after {
+            response.insert_header((RETRY_AFTER, secs));
+        }
+        match version {
+            ResponseVersion::V1 => response.json(ErrorResponse {
+                error: self.message.clone(),
+            }),
+            ResponseVersion::V2 => {
+                let mut error = json!({ "code": self.code, "message": self.message });
+                if let Some(tx_hash) = &self.transaction_hash {
+                    error["transaction_hash"] = json!(tx_hash);
+                }
+                response.json(json!({ "ok": false, "error": error }))
+            }
+        }
+    }
+}
+
+/// A successful result's body, enveloped for v2 clients.
+pub fn envelope<T: Serialize>(version: ResponseVersion, data: &T) -> serde_json::Value {
+    match version {
+        ResponseVersion::V1 => json!(data),
+        ResponseVersion::V2 => json!({ "ok": true, "data": data }),
+    }
+}
+
+/// Renders a successful result, enveloping it for v2 clients.
+pub fn ok<T: Serialize>(version: ResponseVersion, data: &T) -> HttpResponse {
+    match version {
+        // Serialized directly to keep the struct's field order
+        ResponseVersion::V1 => HttpResponse::Ok().json(data),
+        ResponseVersion::V2 => HttpResponse::Ok().json(envelope(version, data)),
+    }
+}
@@ -0,0 +1,292 @@
+use std::{
+    collections::{HashMap, HashSet},
+    net::IpAddr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use actix_web::{http::StatusCode, web, HttpRequest, HttpResponse};
+use alloy_json_rpc::{ErrorPayload, RpcError};
+use alloy_provider::Provider;
+use serde::Deserialize;
+use serde_json::{json, value::RawValue, Value};
+
+use crate::AppState;
+
+/// Read-only methods that may be forwarded. The operator allowlist must be a subset of these.
+pub const READ_ONLY_METHODS: &[&str] = &[
+    "eth_blockNumber",
+    "eth_call",
+    "eth_chainId",
+    "eth_estimateGas",
+    "eth_feeHistory",
+    "eth_gasPrice",
+    "eth_getBalance",
+    "eth_getBlockByHash",
+    "eth_getBlockByNumber",
+    "eth_getCode",
+    "eth_getLogs",
+    "eth_getStorageAt",
+    "eth_getTransactionByHash",
+    "eth_getTransactionCount",
+    "eth_getTransactionReceipt",
+    "eth_maxPriorityFeePerGas",
+    "net_version",
+];
+
+/// Number of tracked clients after which expired rate limit windows are pruned.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Fixed-window request limiter keyed by client IP.
+pub struct RateLimiter {
+    limit: u32,
+    window: Duration,
+    hits: Mutex<HashMap<IpAddr, (Instant, u32)>>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: u32, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            hits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Records a hit for `ip`, returning false if it exceeds the limit for the current window.
+    pub fn check(&self, ip: IpAddr) -> bool {
+        let now = Instant::now();
+        let mut hits = self.hits.lock().unwrap();
+        if hits.len() > PRUNE_THRESHOLD {
+            hits.retain(|_, (start, _)| now.duration_since(*start) < self.window);
+        }
+
+        let entry = hits.entry(ip).or_insert((now, 0));
+        if now.duration_since(entry.0) >= self.window {
+            *entry = (now, 0);
+        }
+        entry.1 += 1;
+        entry.1 <= self.limit
+    }
+}
+
+/// Configuration for the `/rpc` passthrough endpoint.
+pub struct RpcProxy {
+    allowed_methods: HashSet<String>,
+    limiter: RateLimiter,
+}
+
+impl RpcProxy {
+    /// Builds the proxy, rejecting any allowlisted method that is not known to be read-only.
+    pub fn new(methods: &[String], requests_per_minute: u32) -> Result<Self, String> {
+        if let Some(method) = methods
+            .iter()
+            .find(|m| !READ_ONLY_METHODS.contains(&m.as_str()))
+        {
+            return Err(format!("RPC method {} is not a read-only method", method));
+        }
+
+        Ok(Self {
+            allowed_methods: methods.iter().cloned().collect(),
+            limiter: RateLimiter::new(requests_per_minute, Duration::from_secs(60)),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RpcProxyRequest {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// A single JSON-RPC request or a batch of them.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum RpcProxyBody {
+    Single(RpcProxyRequest),
+    Batch(Vec<RpcProxyRequest>),
+}
+
+fn rpc_error(id: &Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+/// Forwards allowlisted JSON-RPC requests to the backing provider. Each request of a batch counts
+/// towards the rate limit and is answered on its own.
+pub async fn proxy_rpc(
+    req: HttpRequest,
+    body: web::Json<RpcProxyBody>,
+    state: web::Data<AppState>,
+) -> HttpResponse {
+    let Some(proxy) = state.rpc_proxy.as_ref() else {
+        return HttpResponse::NotFound().finish();
+    };
+    let client_ip = state.client_ip.resolve(&req);
+
+    match &*body {
+        RpcProxyBody::Single(request) => {
+            let (status, response) = forward(&state, proxy, client_ip, request).await;
+            HttpResponse::build(status).json(response)
+        }
+        RpcProxyBody::Batch(requests) if requests.is_empty() => {
+            HttpResponse::Ok().json(rpc_error(&Value::Null, -32600, "Empty batch"))
+        }
+        RpcProxyBody::Batch(requests) => {
+            let mut responses = Vec::with_capacity(requests.len());
+            for request in requests {
+                responses.push(forward(&state, proxy, client_ip, request).await.1);
+            }
+            HttpResponse::Ok().json(responses)
+        }
+    }
+}
+
+/// Answers one request, forwarding it if the client is within its rate limit and the method is
+/// allowlisted.
+async fn forward(
+    state: &AppState,
+    proxy: &RpcProxy,
+    client_ip: Option<IpAddr>,
+    request: &RpcProxyRequest,
+) -> (StatusCode, Value) {
+    if let Some(ip) = client_ip {
+        if !proxy.limiter.check(ip) {
+            return (
+                StatusCode::TOO_MANY_REQUESTS,
+                rpc_error(&request.id, -32005, "Rate limit exceeded"),
+            );
+        }
+    }
+
+    if !proxy.allowed_methods.contains(&request.method) {
+        return (
+            StatusCode::OK,
+            rpc_error(&request.id, -32601, "Method not allowed"),
+        );
+    }
+
+    let params = if request.params.is_null() {
+        json!([])
+    } else {
+        request.params.clone()
+    };
+
+    match state
+        .provider
+        .raw_request::<_, Box<RawValue>>(request.method.clone().into(), params)
+        .await
+    {
+        Ok(result) => (
+            StatusCode::OK,
+            json!({
+                "jsonrpc": "2.0",
+                "id": request.id,
+                "result": result,
+            }),
+        ),
+        Err(RpcError::ErrorResp(ErrorPayload {
+            code,
+            message,
+            data,
+        })) => (
+            StatusCode::OK,
+            json!({
+                "jsonrpc": "2.0",
+                "id": request.id,
+                "error": { "code": code, "message": message, "data": data },
+            }),
+        ),
+        Err(e) => (
+            StatusCode::BAD_GATEWAY,
+            rpc_error(&request.id, -32603, &format!("Upstream RPC error: {}", e)),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use actix_web::{test::TestRequest, App};
+
+    use super::*;
+    use crate::tests::{mock_rpc, test_state};
+
+    #[test]
+    fn allowlist_must_be_read_only() {
+        let methods = [
+            "eth_blockNumber".to_string(),
+            "eth_sendRawTransaction".to_string(),
+        ];
+        let error = RpcProxy::new(&methods, 60).err().unwrap();
+        assert!(error.contains("eth_sendRawTransaction"));
+        assert!(RpcProxy::new(&methods[..1], 60).is_ok());
+    }
+
+    #[test]
+    fn rate_limit_window_resets() {
+        let limiter = RateLimiter::new(2, Duration::from_millis(50));
+        let ip: IpAddr = "203.0.113.7".parse().unwrap();
+        assert!(limiter.check(ip));
+        assert!(limiter.check(ip));
+        assert!(!limiter.check(ip));
+        assert!(limiter.check("203.0.113.8".parse().unwrap()));
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(limiter.check(ip));
+    }
+
+    async fn call(body: Value) -> (StatusCode, Value) {
+        let rpc_url = mock_rpc(Arc::new(|method, _| match method {
+            "eth_blockNumber" => Ok(json!("0x10")),
+            _ => Err(format!("unexpected {}", method)),
+        }));
+        let mut state = test_state(&rpc_url);
+        state.rpc_proxy = Some(RpcProxy::new(&["eth_blockNumber".to_string()], 60).unwrap());
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(web::Data::new(state))
+                .route("/rpc", web::post().to(proxy_rpc)),
+        )
+        .await;
+
+        let req = TestRequest::post().uri("/rpc").set_json(body).to_request();
+        let response = actix_web::test::call_service(&app, req).await;
+        let status = response.status();
+        (status, actix_web::test::read_body_json(response).await)
+    }
+
+    #[actix_web::test]
+    async fn disallowed_method_is_rejected() {
+        let (status, response) = call(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_sendRawTransaction",
+            "params": ["0x00"],
+        }))
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(response["error"]["code"], -32601);
+        assert_eq!(response["id"], 1);
+    }
+
+    #[actix_web::test]
+    async fn disallowed_method_is_rejected_inside_a_batch() {
+        let (status, response) = call(json!([
+            { "jsonrpc": "2.0", "id": 1, "method": "eth_blockNumber" },
+            { "jsonrpc": "2.0", "id": 2, "method": "eth_sendRawTransaction", "params": ["0x00"] },
+        ]))
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(response[0]["result"], "0x10");
+        assert_eq!(response[1]["error"]["code"], -32601);
+        assert_eq!(response[1]["id"], 2);
+    }
+}
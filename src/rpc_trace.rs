@@ -0,0 +1,231 @@
+use std::{
+    borrow::Cow,
+    task::{Context, Poll},
+};
+
+use alloy_json_rpc::{
+    Id, RequestPacket, Response, ResponsePacket, ResponsePayload, SerializedRequest,
+};
+use alloy_transport::{Transport, TransportError, TransportFut};
+use tower::{Layer, Service};
+use tracing::trace;
+
+/// Methods whose params carry signed payloads or credentials and must never be logged verbatim.
+const REDACTED_METHODS: &[&str] = &["eth_sendRawTransaction", "eth_sign", "eth_signTransaction"];
+
+/// Methods whose result is a signature or signed transaction.
+const REDACTED_RESULT_METHODS: &[&str] = &["eth_sign", "eth_signTransaction"];
+
+/// Transport layer that logs every RPC request and response at trace level when enabled.
+#[derive(Clone, Copy)]
+pub struct RpcTraceLayer {
+    enabled: bool,
+    max_body_bytes: usize,
+}
+
+impl RpcTraceLayer {
+    pub const fn new(enabled: bool, max_body_bytes: usize) -> Self {
+        Self {
+            enabled,
+            max_body_bytes,
+        }
+    }
+}
+
+impl<S> Layer<S> for RpcTraceLayer {
+    type Service = RpcTrace<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RpcTrace {
+            inner,
+            enabled: self.enabled,
+            max_body_bytes: self.max_body_bytes,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RpcTrace<S> {
+    inner: S,
+    enabled: bool,
+    max_body_bytes: usize,
+}
+
+/// Truncates `body` to at most `max` bytes without splitting a UTF-8 character.
+fn truncate_body(body: &str, max: usize) -> Cow<'_, str> {
+    if body.len() <= max {
+        return Cow::Borrowed(body);
+    }
+
+    let mut end = max;
+    while !body.is_char_boundary(end) {
+        end -= 1;
+    }
+    Cow::Owned(format!(
+        "{}...<{} bytes truncated>",
+        &body[..end],
+        body.len() - end
+    ))
+}
+
+/// Renders request params for logging, summarizing signed transactions and credentials.
+fn sanitized_params(request: &SerializedRequest) -> String {
+    let Some(params) = request.params() else {
+        return "[]".to_string();
+    };
+
+    if REDACTED_METHODS.contains(&request.method()) || request.method().starts_with("personal_") {
+        format!("<redacted {} bytes>", params.get().len())
+    } else {
+        params.get().to_string()
+    }
+}
+
+/// Whether the result of `method` must be left out of the logs.
+fn redacts_result(method: &str) -> bool {
+    REDACTED_RESULT_METHODS.contains(&method) || method.starts_with("personal_")
+}
+
+/// Renders a response for logging, summarizing the result of a request in `redacted_ids`.
+fn sanitized_response(response: &Response, redacted_ids: &[Id]) -> String {
+    match &response.payload {
+        ResponsePayload::Success(result) if redacted_ids.contains(&response.id) => format!(
+            r#"{{"id":{},"result":"<redacted {} bytes>"}}"#,
+            serde_json::to_string(&response.id).unwrap_or_default(),
+            result.get().len()
+        ),
+        _ => serde_json::to_string(response).unwrap_or_default(),
+    }
+}
+
+impl<S> Service<RequestPacket> for RpcTrace<S>
+where
+    S: Transport + Clone,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, packet: RequestPacket) -> Self::Future {
+        if !self.enabled {
+            return self.inner.call(packet);
+        }
+
+        let requests = match &packet {
+            RequestPacket::Single(request) => std::slice::from_ref(request),
+            RequestPacket::Batch(requests) => requests.as_slice(),
+        };
+        let max_body_bytes = self.max_body_bytes;
+        let redacted_ids: Vec<Id> = requests
+            .iter()
+            .filter(|request| redacts_result(request.method()))
+            .map(|request| request.id().clone())
+            .collect();
+        for request in requests {
+            trace!(
+                id = %request.id(),
+                method = request.method(),
+                params = %truncate_body(&sanitized_params(request), max_body_bytes),
+                "rpc request"
+            );
+        }
+
+        let response = self.inner.call(packet);
+        Box::pin(async move {
+            let response = response.await;
+            let responses = match &response {
+                Ok(ResponsePacket::Single(response)) => std::slice::from_ref(response),
+                Ok(ResponsePacket::Batch(responses)) => responses.as_slice(),
+                Err(e) => {
+                    trace!(error = %e, "rpc transport error");
+                    &[]
+                }
+            };
+            for response in responses {
+                let body = sanitized_response(response, &redacted_ids);
+                trace!(
+                    body = %truncate_body(&body, max_body_bytes),
+                    "rpc response"
+                );
+            }
+            response
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &'static str, params: serde_json::Value) -> SerializedRequest {
+        alloy_json_rpc::Request::new(method, Id::Number(1), params)
+            .serialize()
+            .unwrap()
+    }
+
+    #[test]
+    fn signed_transactions_and_signing_payloads_are_redacted() {
+        let secret = "0x02f86c0180843b9aca00";
+        for method in [
+            "eth_sendRawTransaction",
+            "eth_sign",
+            "eth_signTransaction",
+            "personal_sign",
+            "personal_unlockAccount",
+        ] {
+            let logged = sanitized_params(&request(method, serde_json::json!([secret])));
+            assert!(logged.starts_with("<redacted "), "{}: {}", method, logged);
+            assert!(!logged.contains(secret));
+        }
+    }
+
+    #[test]
+    fn other_params_are_logged() {
+        let logged = sanitized_params(&request("eth_getBalance", serde_json::json!(["0x01"])));
+        assert_eq!(logged, r#"["0x01"]"#);
+    }
+
+    #[test]
+    fn signed_transaction_results_are_redacted() {
+        let signed = r#""0x02f86c0180843b9aca00""#;
+        let response: Response = serde_json::from_str(&format!(
+            r#"{{"jsonrpc":"2.0","id":1,"result":{}}}"#,
+            signed
+        ))
+        .unwrap();
+
+        let logged = sanitized_response(&response, &[Id::Number(1)]);
+        assert_eq!(logged, r#"{"id":1,"result":"<redacted 24 bytes>"}"#);
+        assert!(sanitized_response(&response, &[Id::Number(2)]).contains(signed));
+        assert!(redacts_result("eth_signTransaction"));
+        assert!(!redacts_result("eth_sendRawTransaction"));
+    }
+
+    #[test]
+    fn truncation_never_splits_a_character() {
+        // "é" is two bytes, the limit falls between them
+        let truncated = truncate_body("abé", 3);
+        assert_eq!(truncated, "ab...<2 bytes truncated>");
+    }
+
+    #[test]
+    fn zero_limit_keeps_only_the_marker() {
+        assert_eq!(truncate_body("abc", 0), "...<3 bytes truncated>");
+        assert_eq!(truncate_body("", 0), "");
+    }
+
+    #[test]
+    fn body_at_the_limit_is_kept_whole() {
+        assert!(matches!(truncate_body("abcd", 4), Cow::Borrowed("abcd")));
+    }
+
+    #[test]
+    fn truncated_body_reports_the_dropped_bytes() {
+        let body = "x".repeat(10);
+        assert_eq!(truncate_body(&body, 4), "xxxx...<6 bytes truncated>");
+    }
+}
@@ -0,0 +1,841 @@
+use std::{collections::HashMap, net::IpAddr, sync::Mutex};
+
+use alloy_primitives::{Address, U256};
+use async_trait::async_trait;
+use clap::ValueEnum;
+use sqlx::{postgres::PgPool, sqlite::SqlitePool, Row};
+
+/// Number of claims returned by a history query.
+pub const HISTORY_LIMIT: i64 = 50;
+
+/// A successful drip.
+#[derive(Clone, Debug)]
+pub struct Claim {
+    pub address: Address,
+    pub ip: Option<IpAddr>,
+    pub amount: U256,
+    pub tx_hash: String,
+    /// Unix timestamp in seconds
+    pub claimed_at: i64,
+    /// Instance that dispensed the claim when several faucets share a store
+    pub faucet_id: Option<String>,
+}
+
+/// Persistence for drips, used for cooldowns, per-IP limits and claim history.
+#[async_trait]
+pub trait ClaimStore: Send + Sync {
+    async fn record_claim(&self, claim: &Claim) -> sqlx::Result<()>;
+
+    /// Records `claim` unless any of `addresses` was claimed after `since`, returning whether it
+    /// was recorded. Checking and recording is atomic, so concurrent requests can't both pass.
+    async fn record_claim_unless_claimed_since(
+        &self,
+        claim: &Claim,
+        addresses: &[Address],
+        since: i64,
+    ) -> sqlx::Result<bool>;
+
+    /// Points the claim recorded under `tx_hash` at `new_tx_hash`.
+    async fn set_claim_tx_hash(&self, tx_hash: &str, new_tx_hash: &str) -> sqlx::Result<()>;
+
+    /// Timestamp of the most recent claim for `address`, if any.
+    async fn last_claim_at(&self, address: Address) -> sqlx::Result<Option<i64>>;
+
+    /// Most recent claims for `address`, newest first.
+    async fn history(&self, address: Address, limit: i64) -> sqlx::Result<Vec<Claim>>;
+
+    /// Number of distinct addresses other than `except` ever funded for requests from `ip`. This
+    /// is kept apart from the claims so pruning old claims doesn't lower it.
+    async fn addresses_funded_from(&self, ip: IpAddr, except: Address) -> sqlx::Result<u64>;
+
+    /// Deletes every claim and the addresses funded per IP, returning how many claims were removed.
+    async fn clear(&self) -> sqlx::Result<u64>;
+
+    /// Deletes the claim for a transaction that did not go through, so it no longer counts as funding
+    /// its address from its IP.
+    async fn remove_claim(&self, tx_hash: &str) -> sqlx::Result<()>;
+
+    /// Deletes claims made before the `cutoff` timestamp, returning how many were removed. The
+    /// addresses funded per IP are kept.
+    async fn prune_before(&self, cutoff: i64) -> sqlx::Result<u64>;
+
+    /// Identity of the chain the stored claims were made on.
+    async fn chain_identity(&self) -> sqlx::Result<Option<String>>;
+
+    async fn set_chain_identity(&self, identity: &str) -> sqlx::Result<()>;
+}
+
+/// Live claims per IP and address. Pruning leaves it alone so per-IP limits count over the
+/// faucet's lifetime.
+const FUNDED_ADDRESSES_TABLE: &str = "CREATE TABLE IF NOT EXISTS funded_addresses (
+    ip TEXT NOT NULL,
+    address TEXT NOT NULL,
+    claims BIGINT NOT NULL,
+    PRIMARY KEY (ip, address)
+)";
+
+/// Counts claims recorded before the table existed, a no-op once it is filled.
+const FUNDED_ADDRESSES_BACKFILL: &str = "INSERT INTO funded_addresses (ip, address, claims)
+    SELECT ip, address, COUNT(*) FROM claims WHERE ip IS NOT NULL GROUP BY ip, address
+    ON CONFLICT (ip, address) DO NOTHING";
+
+/// Counts a recorded claim towards its IP, portable across SQLite and Postgres.
+const COUNT_FUNDED: &str = "INSERT INTO funded_addresses (ip, address, claims) VALUES ($1, $2, 1)
+    ON CONFLICT (ip, address) DO UPDATE SET claims = funded_addresses.claims + 1";
+
+/// Uncounts the claims for a transaction ahead of deleting them.
+const UNCOUNT_FUNDED: &str = "UPDATE funded_addresses SET claims = claims - 1
+    WHERE (ip, address) IN (SELECT ip, address FROM claims WHERE tx_hash = $1)";
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum StoreBackend {
+    Memory,
+    Sqlite,
+    Postgres,
+}
+
+/// Opens the configured backend and creates its schema if needed.
+pub async fn open(backend: StoreBackend, url: Option<&str>) -> sqlx::Result<Box<dyn ClaimStore>> {
+    let url = || {
+        url.ok_or_else(|| {
+            sqlx::Error::Configuration("--store-url is required for this store backend".into())
+        })
+    };
+
+    Ok(match backend {
+        StoreBackend::Memory => Box::new(MemoryClaimStore::default()),
+        StoreBackend::Sqlite => Box::new(SqliteClaimStore::connect(url()?).await?),
+        StoreBackend::Postgres => Box::new(PostgresClaimStore::connect(url()?).await?),
+    })
+}
+
+/// Parses a claim row shared by the SQL backends.
+fn claim_from_row<R: Row>(row: &R) -> sqlx::Result<Claim>
+where
+    for<'r> String: sqlx::Decode<'r, R::Database> + sqlx::Type<R::Database>,
+    for<'r> Option<String>: sqlx::Decode<'r, R::Database> + sqlx::Type<R::Database>,
+    for<'r> i64: sqlx::Decode<'r, R::Database> + sqlx::Type<R::Database>,
+    for<'c> &'c str: sqlx::ColumnIndex<R>,
+{
+    let decode = |column: &str, e: String| sqlx::Error::ColumnDecode {
+        index: column.to_string(),
+        source: e.into(),
+    };
+
+    let address: String = row.try_get("address")?;
+    let ip: Option<String> = row.try_get("ip")?;
+    let amount: String = row.try_get("amount")?;
+    Ok(Claim {
+        address: address
+            .parse()
+            .map_err(|e: alloy_primitives::hex::FromHexError| decode("address", e.to_string()))?,
+        ip: ip
+            .map(|ip| ip.parse())
+            .transpose()
+            .map_err(|e: std::net::AddrParseError| decode("ip", e.to_string()))?,
+        amount: U256::from_str_radix(&amount, 10).map_err(|e| decode("amount", e.to_string()))?,
+        tx_hash: row.try_get("tx_hash")?,
+        claimed_at: row.try_get("claimed_at")?,
+        faucet_id: row.try_get("faucet_id")?,
+    })
+}
+
+/// Claims held in process memory, lost on restart.
+#[derive(Default)]
+pub struct MemoryClaimStore {
+    claims: Mutex<Vec<Claim>>,
+    /// Number of live claims per IP and address, entries are never pruned
+    funded: Mutex<HashMap<(IpAddr, Address), u64>>,
+    chain_identity: Mutex<Option<String>>,
+}
+
+impl MemoryClaimStore {
+    fn count_funded(&self, claim: &Claim) {
+        if let Some(ip) = claim.ip {
+            *self
+                .funded
+                .lock()
+                .unwrap()
+                .entry((ip, claim.address))
+                .or_default() += 1;
+        }
+    }
+}
+
+#[async_trait]
+impl ClaimStore for MemoryClaimStore {
+    async fn record_claim(&self, claim: &Claim) -> sqlx::Result<()> {
+        self.claims.lock().unwrap().push(claim.clone());
+        self.count_funded(claim);
+        Ok(())
+    }
+
+    async fn record_claim_unless_claimed_since(
+        &self,
+        claim: &Claim,
+        addresses: &[Address],
+        since: i64,
+    ) -> sqlx::Result<bool> {
+        let mut claims = self.claims.lock().unwrap();
+        if claims
+            .iter()
+            .any(|c| addresses.contains(&c.address) && c.claimed_at > since)
+        {
+            return Ok(false);
+        }
+        claims.push(claim.clone());
+        self.count_funded(claim);
+        Ok(true)
+    }
+
+    async fn set_claim_tx_hash(&self, tx_hash: &str, new_tx_hash: &str) -> sqlx::Result<()> {
+        for claim in self.claims.lock().unwrap().iter_mut() {
+            if claim.tx_hash == tx_hash {
+                claim.tx_hash = new_tx_hash.to_string();
+            }
+        }
+        Ok(())
+    }
+
+    async fn last_claim_at(&self, address: Address) -> sqlx::Result<Option<i64>> {
+        let claims = self.claims.lock().unwrap();
+        Ok(claims
+            .iter()
+            .filter(|c| c.address == address)
+            .map(|c| c.claimed_at)
+            .max())
+    }
+
+    async fn history(&self, address: Address, limit: i64) -> sqlx::Result<Vec<Claim>> {
+        let claims = self.claims.lock().unwrap();
+        let mut history: Vec<Claim> = claims
+            .iter()
+            .rev()
+            .filter(|c| c.address == address)
+            .cloned()
+            .collect();
+        // Stable, so claims made in the same second stay newest first
+        history.sort_by_key(|c| std::cmp::Reverse(c.claimed_at));
+        history.truncate(limit.max(0) as usize);
+        Ok(history)
+    }
+
+    async fn addresses_funded_from(&self, ip: IpAddr, except: Address) -> sqlx::Result<u64> {
+        let funded = self.funded.lock().unwrap();
+        Ok(funded
+            .iter()
+            .filter(|((funded_ip, address), claims)| {
+                *funded_ip == ip && *address != except && **claims > 0
+            })
+            .count() as u64)
+    }
+
+    async fn clear(&self) -> sqlx::Result<u64> {
+        let mut claims = self.claims.lock().unwrap();
+        let cleared = claims.len() as u64;
+        claims.clear();
+        self.funded.lock().unwrap().clear();
+        Ok(cleared)
+    }
+
+    async fn remove_claim(&self, tx_hash: &str) -> sqlx::Result<()> {
+        let mut claims = self.claims.lock().unwrap();
+        let mut funded = self.funded.lock().unwrap();
+        claims.retain(|c| {
+            if c.tx_hash != tx_hash {
+                return true;
+            }
+            if let Some(count) = c.ip.and_then(|ip| funded.get_mut(&(ip, c.address))) {
+                *count = count.saturating_sub(1);
+            }
+            false
+        });
+        Ok(())
+    }
+
+    async fn prune_before(&self, cutoff: i64) -> sqlx::Result<u64> {
+        let mut claims = self.claims.lock().unwrap();
+        let before = claims.len();
+        claims.retain(|c| c.claimed_at >= cutoff);
+        Ok((before - claims.len()) as u64)
+    }
+
+    async fn chain_identity(&self) -> sqlx::Result<Option<String>> {
+        Ok(self.chain_identity.lock().unwrap().clone())
+    }
+
+    async fn set_chain_identity(&self, identity: &str) -> sqlx::Result<()> {
+        *self.chain_identity.lock().unwrap() = Some(identity.to_string());
+        Ok(())
+    }
+}
+
+pub struct SqliteClaimStore {
+    pool: SqlitePool,
+}
+
+impl SqliteClaimStore {
+    async fn connect(url: &str) -> sqlx::Result<Self> {
+        let pool = SqlitePool::connect(url).await?;
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS claims (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                address TEXT NOT NULL,
+                ip TEXT,
+                amount TEXT NOT NULL,
+                tx_hash TEXT NOT NULL,
+                claimed_at INTEGER NOT NULL,
+                faucet_id TEXT
+            )",
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS claims_address ON claims (address, claimed_at)")
+            .execute(&pool)
+            .await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS claims_claimed_at ON claims (claimed_at)")
+            .execute(&pool)
+            .await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS claims_ip ON claims (ip)")
+            .execute(&pool)
+            .await?;
+        sqlx::query("CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value TEXT NOT NULL)")
+            .execute(&pool)
+            .await?;
+        sqlx::query(FUNDED_ADDRESSES_TABLE).execute(&pool).await?;
+        sqlx::query(FUNDED_ADDRESSES_BACKFILL)
+            .execute(&pool)
+            .await?;
+        Ok(Self { pool })
+    }
+}
+
+#[async_trait]
+impl ClaimStore for SqliteClaimStore {
+    async fn record_claim(&self, claim: &Claim) -> sqlx::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "INSERT INTO claims (address, ip, amount, tx_hash, claimed_at, faucet_id)
+             VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(claim.address.to_string())
+        .bind(claim.ip.map(|ip| ip.to_string()))
+        .bind(claim.amount.to_string())
+        .bind(&claim.tx_hash)
+        .bind(claim.claimed_at)
+        .bind(&claim.faucet_id)
+        .execute(&mut *tx)
+        .await?;
+        if let Some(ip) = claim.ip {
+            sqlx::query(COUNT_FUNDED)
+                .bind(ip.to_string())
+                .bind(claim.address.to_string())
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await
+    }
+
+    async fn record_claim_unless_claimed_since(
+        &self,
+        claim: &Claim,
+        addresses: &[Address],
+        since: i64,
+    ) -> sqlx::Result<bool> {
+        // A single statement, so SQLite's write lock covers the check
+        let placeholders = vec!["?"; addresses.len()].join(", ");
+        let sql = format!(
+            "INSERT INTO claims (address, ip, amount, tx_hash, claimed_at, faucet_id)
+             SELECT ?, ?, ?, ?, ?, ?
+             WHERE NOT EXISTS (
+                 SELECT 1 FROM claims WHERE address IN ({}) AND claimed_at > ?
+             )",
+            placeholders
+        );
+        let mut query = sqlx::query(&sql)
+            .bind(claim.address.to_string())
+            .bind(claim.ip.map(|ip| ip.to_string()))
+            .bind(claim.amount.to_string())
+            .bind(&claim.tx_hash)
+            .bind(claim.claimed_at)
+            .bind(&claim.faucet_id);
+        for address in addresses {
+            query = query.bind(address.to_string());
+        }
+        let mut tx = self.pool.begin().await?;
+        let recorded = query.bind(since).execute(&mut *tx).await?.rows_affected() == 1;
+        if let (true, Some(ip)) = (recorded, claim.ip) {
+            sqlx::query(COUNT_FUNDED)
+                .bind(ip.to_string())
+                .bind(claim.address.to_string())
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(recorded)
+    }
+
+    async fn set_claim_tx_hash(&self, tx_hash: &str, new_tx_hash: &str) -> sqlx::Result<()> {
+        sqlx::query("UPDATE claims SET tx_hash = ? WHERE tx_hash = ?")
+            .bind(new_tx_hash)
+            .bind(tx_hash)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn last_claim_at(&self, address: Address) -> sqlx::Result<Option<i64>> {
+        sqlx::query_scalar("SELECT MAX(claimed_at) FROM claims WHERE address = ?")
+            .bind(address.to_string())
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    async fn history(&self, address: Address, limit: i64) -> sqlx::Result<Vec<Claim>> {
+        sqlx::query(
+            "SELECT address, ip, amount, tx_hash, claimed_at, faucet_id FROM claims
+             WHERE address = ? ORDER BY claimed_at DESC, id DESC LIMIT ?",
+        )
+        .bind(address.to_string())
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(claim_from_row)
+        .collect()
+    }
+
+    async fn addresses_funded_from(&self, ip: IpAddr, except: Address) -> sqlx::Result<u64> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM funded_addresses WHERE ip = ? AND address != ? AND claims > 0",
+        )
+        .bind(ip.to_string())
+        .bind(except.to_string())
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(count as u64)
+    }
+
+    async fn clear(&self) -> sqlx::Result<u64> {
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query("DELETE FROM claims").execute(&mut *tx).await?;
+        sqlx::query("DELETE FROM funded_addresses")
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn remove_claim(&self, tx_hash: &str) -> sqlx::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(UNCOUNT_FUNDED)
+            .bind(tx_hash)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM claims WHERE tx_hash = ?")
+            .bind(tx_hash)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await
+    }
+
+    async fn prune_before(&self, cutoff: i64) -> sqlx::Result<u64> {
+        let result = sqlx::query("DELETE FROM claims WHERE claimed_at < ?")
+            .bind(cutoff)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn chain_identity(&self) -> sqlx::Result<Option<String>> {
+        sqlx::query_scalar("SELECT value FROM meta WHERE key = 'chain_identity'")
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    async fn set_chain_identity(&self, identity: &str) -> sqlx::Result<()> {
+        sqlx::query(
+            "INSERT INTO meta (key, value) VALUES ('chain_identity', ?)
+             ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+        )
+        .bind(identity)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+pub struct PostgresClaimStore {
+    pool: PgPool,
+}
+
+impl PostgresClaimStore {
+    async fn connect(url: &str) -> sqlx::Result<Self> {
+        let pool = PgPool::connect(url).await?;
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS claims (
+                id BIGSERIAL PRIMARY KEY,
+                address TEXT NOT NULL,
+                ip TEXT,
+                amount TEXT NOT NULL,
+                tx_hash TEXT NOT NULL,
+                claimed_at BIGINT NOT NULL,
+                faucet_id TEXT
+            )",
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS claims_address ON claims (address, claimed_at)")
+            .execute(&pool)
+            .await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS claims_claimed_at ON claims (claimed_at)")
+            .execute(&pool)
+            .await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS claims_ip ON claims (ip)")
+            .execute(&pool)
+            .await?;
+        sqlx::query("CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value TEXT NOT NULL)")
+            .execute(&pool)
+            .await?;
+        sqlx::query(FUNDED_ADDRESSES_TABLE).execute(&pool).await?;
+        sqlx::query(FUNDED_ADDRESSES_BACKFILL)
+            .execute(&pool)
+            .await?;
+        Ok(Self { pool })
+    }
+}
+
+#[async_trait]
+impl ClaimStore for PostgresClaimStore {
+    async fn record_claim(&self, claim: &Claim) -> sqlx::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "INSERT INTO claims (address, ip, amount, tx_hash, claimed_at, faucet_id)
+             VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(claim.address.to_string())
+        .bind(claim.ip.map(|ip| ip.to_string()))
+        .bind(claim.amount.to_string())
+        .bind(&claim.tx_hash)
+        .bind(claim.claimed_at)
+        .bind(&claim.faucet_id)
+        .execute(&mut *tx)
+        .await?;
+        if let Some(ip) = claim.ip {
+            sqlx::query(COUNT_FUNDED)
+                .bind(ip.to_string())
+                .bind(claim.address.to_string())
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await
+    }
+
+    async fn record_claim_unless_claimed_since(
+        &self,
+        claim: &Claim,
+        addresses: &[Address],
+        since: i64,
+    ) -> sqlx::Result<bool> {
+        let mut addresses: Vec<String> = addresses.iter().map(Address::to_string).collect();
+        addresses.sort();
+        // Concurrent transactions could both miss the other's insert, so serialize per address.
+        // Locks are taken in sorted order to avoid deadlocks.
+        let mut tx = self.pool.begin().await?;
+        for address in &addresses {
+            sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+                .bind(address)
+                .execute(&mut *tx)
+                .await?;
+        }
+        let result = sqlx::query(
+            "INSERT INTO claims (address, ip, amount, tx_hash, claimed_at, faucet_id)
+             SELECT $1, $2, $3, $4, $5, $6
+             WHERE NOT EXISTS (
+                 SELECT 1 FROM claims WHERE address = ANY($7) AND claimed_at > $8
+             )",
+        )
+        .bind(claim.address.to_string())
+        .bind(claim.ip.map(|ip| ip.to_string()))
+        .bind(claim.amount.to_string())
+        .bind(&claim.tx_hash)
+        .bind(claim.claimed_at)
+        .bind(&claim.faucet_id)
+        .bind(&addresses)
+        .bind(since)
+        .execute(&mut *tx)
+        .await?;
+        let recorded = result.rows_affected() == 1;
+        if let (true, Some(ip)) = (recorded, claim.ip) {
+            sqlx::query(COUNT_FUNDED)
+                .bind(ip.to_string())
+                .bind(claim.address.to_string())
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(recorded)
+    }
+
+    async fn set_claim_tx_hash(&self, tx_hash: &str, new_tx_hash: &str) -> sqlx::Result<()> {
+        sqlx::query("UPDATE claims SET tx_hash = $1 WHERE tx_hash = $2")
+            .bind(new_tx_hash)
+            .bind(tx_hash)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn last_claim_at(&self, address: Address) -> sqlx::Result<Option<i64>> {
+        sqlx::query_scalar("SELECT MAX(claimed_at) FROM claims WHERE address = $1")
+            .bind(address.to_string())
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    async fn history(&self, address: Address, limit: i64) -> sqlx::Result<Vec<Claim>> {
+        sqlx::query(
+            "SELECT address, ip, amount, tx_hash, claimed_at, faucet_id FROM claims
+             WHERE address = $1 ORDER BY claimed_at DESC, id DESC LIMIT $2",
+        )
+        .bind(address.to_string())
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(claim_from_row)
+        .collect()
+    }
+
+    async fn addresses_funded_from(&self, ip: IpAddr, except: Address) -> sqlx::Result<u64> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM funded_addresses WHERE ip = $1 AND address != $2 AND claims > 0",
+        )
+        .bind(ip.to_string())
+        .bind(except.to_string())
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(count as u64)
+    }
+
+    async fn clear(&self) -> sqlx::Result<u64> {
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query("DELETE FROM claims").execute(&mut *tx).await?;
+        sqlx::query("DELETE FROM funded_addresses")
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn remove_claim(&self, tx_hash: &str) -> sqlx::Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(UNCOUNT_FUNDED)
+            .bind(tx_hash)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM claims WHERE tx_hash = $1")
+            .bind(tx_hash)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await
+    }
+
+    async fn prune_before(&self, cutoff: i64) -> sqlx::Result<u64> {
+        let result = sqlx::query("DELETE FROM claims WHERE claimed_at < $1")
+            .bind(cutoff)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn chain_identity(&self) -> sqlx::Result<Option<String>> {
+        sqlx::query_scalar("SELECT value FROM meta WHERE key = 'chain_identity'")
+            .fetch_optional(&self.pool)
+            .await
+    }
+
+    async fn set_chain_identity(&self, identity: &str) -> sqlx::Result<()> {
+        sqlx::query(
+            "INSERT INTO meta (key, value) VALUES ('chain_identity', $1)
+             ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+        )
+        .bind(identity)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claim(address: Address, tx_hash: &str, claimed_at: i64) -> Claim {
+        Claim {
+            address,
+            ip: None,
+            amount: U256::from(1),
+            tx_hash: tx_hash.to_string(),
+            claimed_at,
+            faucet_id: None,
+        }
+    }
+
+    async fn sqlite_store(name: &str) -> (SqliteClaimStore, std::path::PathBuf) {
+        let path = std::env::temp_dir().join(format!("claims-{}-{}.db", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let url = format!("sqlite://{}?mode=rwc", path.display());
+        (SqliteClaimStore::connect(&url).await.unwrap(), path)
+    }
+
+    async fn rejects_claims_within_the_cooldown(store: &dyn ClaimStore) {
+        let address = Address::repeat_byte(1);
+        let other = Address::repeat_byte(2);
+        store
+            .record_claim(&claim(address, "0x01", 1_000))
+            .await
+            .unwrap();
+
+        let recorded = store
+            .record_claim_unless_claimed_since(&claim(other, "0x02", 1_050), &[other, address], 900)
+            .await
+            .unwrap();
+        assert!(!recorded);
+        let recorded = store
+            .record_claim_unless_claimed_since(&claim(address, "0x03", 2_000), &[address], 1_000)
+            .await
+            .unwrap();
+        assert!(recorded);
+        assert_eq!(store.last_claim_at(address).await.unwrap(), Some(2_000));
+        assert_eq!(store.last_claim_at(other).await.unwrap(), None);
+    }
+
+    async fn concurrent_claims_record_once(store: &dyn ClaimStore) {
+        let address = Address::repeat_byte(3);
+        let first = claim(address, "sending:1", 1_000);
+        let second = claim(address, "sending:2", 1_000);
+        let keys = [address];
+        let (first, second) = futures::join!(
+            store.record_claim_unless_claimed_since(&first, &keys, 0),
+            store.record_claim_unless_claimed_since(&second, &keys, 0),
+        );
+        assert!(first.unwrap() ^ second.unwrap());
+    }
+
+    async fn placeholder_hash_is_replaced(store: &dyn ClaimStore) {
+        let address = Address::repeat_byte(4);
+        store
+            .record_claim(&claim(address, "sending:1", 1_000))
+            .await
+            .unwrap();
+        store.set_claim_tx_hash("sending:1", "0xabc").await.unwrap();
+
+        store.remove_claim("sending:1").await.unwrap();
+        assert_eq!(store.last_claim_at(address).await.unwrap(), Some(1_000));
+        store.remove_claim("0xabc").await.unwrap();
+        assert_eq!(store.last_claim_at(address).await.unwrap(), None);
+    }
+
+    async fn funded_addresses_survive_pruning(store: &dyn ClaimStore) {
+        let ip: IpAddr = "203.0.113.7".parse().unwrap();
+        let funded_from = |address: u8, tx_hash: &str, claimed_at: i64| Claim {
+            ip: Some(ip),
+            ..claim(Address::repeat_byte(address), tx_hash, claimed_at)
+        };
+        store
+            .record_claim(&funded_from(5, "0x05", 100))
+            .await
+            .unwrap();
+        store
+            .record_claim(&funded_from(6, "0x06", 200))
+            .await
+            .unwrap();
+        store
+            .record_claim(&funded_from(7, "0x07", 300))
+            .await
+            .unwrap();
+        let except = Address::repeat_byte(9);
+
+        assert_eq!(store.prune_before(250).await.unwrap(), 2);
+        assert_eq!(store.addresses_funded_from(ip, except).await.unwrap(), 3);
+        // A drip that didn't go through doesn't count
+        store.remove_claim("0x07").await.unwrap();
+        assert_eq!(store.addresses_funded_from(ip, except).await.unwrap(), 2);
+        assert_eq!(
+            store
+                .addresses_funded_from(ip, Address::repeat_byte(5))
+                .await
+                .unwrap(),
+            1
+        );
+    }
+
+    async fn history_lists_newest_claims_first(store: &dyn ClaimStore) {
+        let address = Address::repeat_byte(8);
+        for (tx_hash, claimed_at) in [("0x0a", 100), ("0x0c", 300), ("0x0b", 200)] {
+            store
+                .record_claim(&claim(address, tx_hash, claimed_at))
+                .await
+                .unwrap();
+        }
+        store
+            .record_claim(&claim(Address::repeat_byte(9), "0x0d", 400))
+            .await
+            .unwrap();
+
+        let history = store.history(address, 2).await.unwrap();
+        let hashes: Vec<_> = history.iter().map(|c| c.tx_hash.as_str()).collect();
+        assert_eq!(hashes, ["0x0c", "0x0b"]);
+        assert_eq!(history[0].amount, U256::from(1));
+        assert!(store
+            .history(Address::repeat_byte(10), HISTORY_LIMIT)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[actix_web::test]
+    async fn memory_store_lists_history() {
+        history_lists_newest_claims_first(&MemoryClaimStore::default()).await;
+    }
+
+    #[actix_web::test]
+    async fn sqlite_store_lists_history() {
+        let (store, path) = sqlite_store("history").await;
+        history_lists_newest_claims_first(&store).await;
+        store.pool.close().await;
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn memory_store_checks_cooldown_atomically() {
+        let store = MemoryClaimStore::default();
+        rejects_claims_within_the_cooldown(&store).await;
+        concurrent_claims_record_once(&store).await;
+        placeholder_hash_is_replaced(&store).await;
+    }
+
+    #[actix_web::test]
+    async fn memory_store_keeps_funded_addresses() {
+        funded_addresses_survive_pruning(&MemoryClaimStore::default()).await;
+    }
+
+    #[actix_web::test]
+    async fn sqlite_store_keeps_funded_addresses() {
+        let (store, path) = sqlite_store("funded").await;
+        funded_addresses_survive_pruning(&store).await;
+        store.pool.close().await;
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[actix_web::test]
+    async fn sqlite_store_checks_cooldown_atomically() {
+        let (store, path) = sqlite_store("cooldown").await;
+        rejects_claims_within_the_cooldown(&store).await;
+        concurrent_claims_record_once(&store).await;
+        placeholder_hash_is_replaced(&store).await;
+        store.pool.close().await;
+        std::fs::remove_file(&path).unwrap();
+    }
+}